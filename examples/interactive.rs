@@ -1,26 +1,30 @@
 use raytox::algebra::{Point3, Vector3};
-use raytox::camera::Camera;
+use raytox::background::{Environment, EnvironmentMap};
+use raytox::camera::{Camera, Projection};
 use raytox::color::Color;
 use raytox::entity::{Entity, Transformable};
-use raytox::geometry::Cube;
 use raytox::geometry::{Plane, Sphere};
 use raytox::interactive::InteractiveWindow;
-use raytox::light::PointLight;
+use raytox::light::DirectionalLight;
 use raytox::material::Phong;
 use raytox::scene::Scene;
 use raytox::texture::Texture;
 use raytox::texture::{Checker, Image};
 
 fn main() {
-    let mut camera = Camera::new(Point3::new(0.0, 3.0, 0.0));
+    let mut camera =
+        Camera::new(Point3::new(0.0, 3.0, 0.0)).with_projection(Projection::Perspective {
+            fov: 70.0_f64.to_radians(),
+        });
     camera.look_at(Point3::new(0.0, 0.0, 30.0));
 
     let mut scene = Scene::new(
         800,
         400,
-        70.0_f64.to_radians(),
         camera,
-        Color::new(0.00, 0.03, 0.03),
+        Environment::new(EnvironmentMap::CrossCubeMap(Image::from(
+            image::open("assets/space_cubemap.jpg").unwrap(),
+        ))),
     );
 
     scene.add_entity(
@@ -33,16 +37,6 @@ fn main() {
             ))
             .translate(Vector3::new(0.0, -15.0, 0.0))
     );
-    scene.add_entity(
-        Entity::from(Cube::default().with_flipped_normals())
-            .with_material(Phong::new(
-                Color::WHITE * 0.03,
-                Image::from(image::open("assets/space_cubemap.jpg").unwrap()),
-                Color::WHITE * 0.01,
-                20.0,
-            ))
-            .scale(Vector3::new(2000000.0, 2000000.0, 2000000.0))
-    );
     scene.add_entity(
         Entity::from(Sphere::default())
             .with_material(Phong::random_color().with_reflectance(0.1))
@@ -62,12 +56,12 @@ fn main() {
             .translate(Vector3::new(15.0, 0.0, 30.0))
     );
 
-    scene.add_light(PointLight::new(
-        Point3::new(0.0, 50.0, 0.0),
+    scene.add_light(DirectionalLight::new(
+        Vector3::new(0.0, -5.0, 3.0),
         Color::WHITE,
         Color::WHITE,
         Color::WHITE,
-        f64::INFINITY,
+        1.0,
     ));
 
     let mut window = InteractiveWindow::new(scene);
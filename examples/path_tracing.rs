@@ -0,0 +1,93 @@
+use raytox::algebra::{Point3, Vector3};
+use raytox::camera::{Camera, Projection};
+use raytox::color::Color;
+use raytox::entity::{Entity, Transformable};
+use raytox::geometry::{Axis, Cube, Plane, Sphere};
+use raytox::integrator::PathTracer;
+use raytox::light::PointLight;
+use raytox::material::Phong;
+use raytox::scene::Scene;
+use raytox::tone_map::{DisplayTransform, ToneMap};
+
+fn diffuse(color: Color) -> Phong {
+    Phong::new(Color::BLACK, color, Color::BLACK, 1.0)
+}
+
+fn main() {
+    let mut camera =
+        Camera::new(Point3::new(0.0, 0.0, -24.0)).with_projection(Projection::Perspective {
+            fov: 50.0_f64.to_radians(),
+        });
+    camera.look_at(Point3::O);
+
+    let mut scene = Scene::new(300, 300, camera, Color::BLACK)
+        .with_integrator(PathTracer::default())
+        .with_samples_per_pixel(16)
+        .with_display(DisplayTransform::new(ToneMap::Aces).with_srgb())
+        .with_progress_bar();
+
+    // box open towards the camera, with a red wall on the left and a green one on the right
+    let walls = [
+        (
+            Point3::new(0.0, -10.0, 0.0),
+            Vector3::J,
+            Color::WHITE * 0.75,
+        ),
+        (
+            Point3::new(0.0, 10.0, 0.0),
+            -Vector3::J,
+            Color::WHITE * 0.75,
+        ),
+        (
+            Point3::new(0.0, 0.0, 10.0),
+            -Vector3::K,
+            Color::WHITE * 0.75,
+        ),
+        (
+            Point3::new(-10.0, 0.0, 0.0),
+            Vector3::I,
+            Color::new(0.63, 0.06, 0.05),
+        ),
+        (
+            Point3::new(10.0, 0.0, 0.0),
+            -Vector3::I,
+            Color::new(0.14, 0.45, 0.09),
+        ),
+    ];
+    for (origin, normal, color) in walls.iter() {
+        scene.add_entity(Entity::from(Plane::new(*origin, *normal)).with_material(diffuse(*color)));
+    }
+
+    scene.add_entity(
+        Entity::from(Cube::default())
+            .scale(Vector3::new(6.0, 12.0, 6.0))
+            .rotate(Axis::Y, 20.0)
+            .translate(Vector3::new(-3.5, -4.0, 3.0))
+            .with_material(diffuse(Color::WHITE * 0.75)),
+    );
+    scene.add_entity(
+        Entity::from(Sphere::new(Point3::new(4.0, -6.5, -2.0), 3.5)).with_material(
+            Phong::new(Color::BLACK, Color::BLACK, Color::BLACK, 1.0)
+                .with_transparency(1.0)
+                .with_refractive_index(1.5),
+        ),
+    );
+
+    scene.add_light(PointLight::new(
+        Point3::new(0.0, 6.0, -2.0),
+        Color::WHITE,
+        Color::WHITE,
+        Color::WHITE,
+        60.0,
+    ));
+
+    let display = scene.display;
+    scene.render_progressive(4, |pass, film| {
+        // keep an up to date preview while the render converges
+        if pass % 2 == 0 {
+            film.to_image(&display)
+                .save("output/path_tracing.png")
+                .unwrap();
+        }
+    });
+}
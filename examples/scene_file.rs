@@ -0,0 +1,28 @@
+use std::env;
+
+use raytox::scene::Scene;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let path = args.get(1).map_or("scenes/basic.toml", |x| x.as_str());
+    let output = args.get(2).map_or("output/scene_file.png", |x| x.as_str());
+
+    let scene = match Scene::from_file(path) {
+        Ok(scene) => scene,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // floating point formats get the linear radiance, anything else the display transform
+    let result = if [".hdr", ".pfm", ".exr"].iter().any(|x| output.ends_with(x)) {
+        scene.render_film().to_linear_image().save(output)
+    } else {
+        scene.render().save(output).map_err(|e| e.to_string())
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -1,5 +1,5 @@
 use raytox::algebra::{Point3, Vector3};
-use raytox::camera::Camera;
+use raytox::camera::{Camera, Projection};
 use raytox::color::Color;
 use raytox::entity::{Entity, Transformable};
 use raytox::geometry::Plane;
@@ -11,16 +11,13 @@ use raytox::texture::Checker;
 use raytox::texture::Texture;
 
 fn main() {
-    let mut camera = Camera::new(Point3::new(0.0, 0.0, -35.0));
+    let mut camera =
+        Camera::new(Point3::new(0.0, 0.0, -35.0)).with_projection(Projection::Perspective {
+            fov: 70.0_f64.to_radians(),
+        });
     camera.look_at(Point3::O);
 
-    let mut scene = Scene::new(
-        800,
-        600,
-        70.0_f64.to_radians(),
-        camera,
-        Color::new(0.00, 0.03, 0.03),
-    );
+    let mut scene = Scene::new(800, 600, camera, Color::new(0.00, 0.03, 0.03));
 
     scene.add_entity(
         Entity::from(Plane::default())
@@ -0,0 +1,81 @@
+use raytox::algebra::{Point3, Vector3};
+use raytox::animation::{Animation, EntityTracks, Interpolation, PointLightTracks, Track};
+use raytox::camera::{Camera, Projection};
+use raytox::color::Color;
+use raytox::entity::{Entity, Transformable};
+use raytox::geometry::{Cube, Plane};
+use raytox::light::PointLight;
+use raytox::material::Phong;
+use raytox::scene::Scene;
+use raytox::texture::Checker;
+use raytox::texture::Texture;
+
+fn main() {
+    let camera =
+        Camera::new(Point3::new(0.0, 8.0, -25.0)).with_projection(Projection::Perspective {
+            fov: 50.0_f64.to_radians(),
+        });
+
+    let mut scene = Scene::new(320, 240, camera, Color::new(0.00, 0.03, 0.03));
+
+    scene.add_entity(
+        Entity::from(Plane::default())
+            .with_material(Phong::new(
+                Color::WHITE * 0.03,
+                Texture::new(5.0, Checker::new(Color::WHITE * 0.4, Color::WHITE * 0.03)),
+                Color::WHITE,
+                20.0,
+            ))
+            .translate(Vector3::new(0.0, -5.0, 0.0)),
+    );
+    scene.add_entity(
+        Entity::from(Cube::default())
+            .with_material(Phong::new(
+                Color::WHITE * 0.03,
+                Color::new(0.8, 0.3, 0.1),
+                Color::WHITE,
+                20.0,
+            ))
+            .scale(Vector3::new(6.0, 6.0, 6.0))
+            .translate(Vector3::new(0.0, -2.0, 0.0)),
+    );
+
+    scene.add_light(PointLight::new(
+        Point3::new(-10.0, 15.0, -15.0),
+        Color::WHITE,
+        Color::WHITE,
+        Color::WHITE,
+        500.0,
+    ));
+
+    // a full turn of the cube in 2 seconds while the camera rises, and the light fading in
+    let animation = Animation::new()
+        .with_camera(
+            Track::new()
+                .with_key(0.0, Point3::new(0.0, 2.0, -25.0), Interpolation::Bezier)
+                .with_key(2.0, Point3::new(0.0, 12.0, -20.0), Interpolation::Linear),
+            Track::new().with_key(0.0, Point3::new(0.0, -2.0, 0.0), Interpolation::Linear),
+        )
+        .with_entity(
+            1,
+            EntityTracks {
+                rotation: Track::new()
+                    .with_key(0.0, Vector3::ZERO, Interpolation::Linear)
+                    .with_key(2.0, Vector3::new(0.0, 360.0, 0.0), Interpolation::Linear),
+                ..EntityTracks::default()
+            },
+        )
+        .with_point_light(
+            0,
+            PointLightTracks {
+                intensity: Track::new()
+                    .with_key(0.0, 50.0, Interpolation::Bezier)
+                    .with_key(1.0, 500.0, Interpolation::Linear),
+                ..PointLightTracks::default()
+            },
+        );
+
+    animation
+        .render_sequence(&mut scene, 1..=48, 24.0, "output/turntable")
+        .unwrap();
+}
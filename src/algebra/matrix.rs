@@ -1,3 +1,4 @@
+use std::ops::Add;
 use std::ops::Index;
 use std::ops::IndexMut;
 use std::ops::Mul;
@@ -244,6 +245,21 @@ impl IndexMut<usize> for Matrix4 {
     }
 }
 
+impl Add<Matrix4> for Matrix4 {
+    type Output = Matrix4;
+
+    fn add(self, rhs: Matrix4) -> Self::Output {
+        let mut ret = Self::new();
+        for i in 0..4 {
+            for j in 0..4 {
+                ret[i][j] = self[i][j] + rhs[i][j]
+            }
+        }
+
+        ret
+    }
+}
+
 impl Mul<Matrix4> for Matrix4 {
     type Output = Matrix4;
 
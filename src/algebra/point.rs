@@ -1,4 +1,4 @@
-use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::ops::{Add, Div, Index, Mul, Neg, Sub};
 
 use crate::{entity::Transformable, geometry::Axis};
 
@@ -22,6 +22,30 @@ impl From<[f64; 2]> for Point2 {
     }
 }
 
+impl Add for Point2 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl Mul<f64> for Point2 {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self::Output {
+        Self::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl Mul<Point2> for f64 {
+    type Output = Point2;
+
+    fn mul(self, rhs: Point2) -> Self::Output {
+        Point2::new(self * rhs.x, self * rhs.y)
+    }
+}
+
 impl Div<f64> for Point2 {
     type Output = Self;
 
@@ -97,6 +121,18 @@ impl Mul<Point3> for f64 {
     }
 }
 
+impl Index<Axis> for Point3 {
+    type Output = f64;
+
+    fn index(&self, axis: Axis) -> &Self::Output {
+        match axis {
+            Axis::X => &self.x,
+            Axis::Y => &self.y,
+            Axis::Z => &self.z,
+        }
+    }
+}
+
 impl Transformable for Point3 {
     fn translate(self, translation: Vector3) -> Self {
         Matrix4::translation(translation) * self
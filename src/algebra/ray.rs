@@ -9,11 +9,27 @@ use super::Matrix4;
 pub struct Ray {
     pub origin: Point3,
     pub dir: Vector3,
+    /// Instant the ray is traced at, which moving entities are intersected at.
+    pub time: f64,
 }
 
 impl Ray {
     pub fn new(origin: Point3, dir: Vector3) -> Self {
-        Self { origin, dir }
+        Self {
+            origin,
+            dir,
+            time: 0.0,
+        }
+    }
+
+    pub fn with_time(mut self, time: f64) -> Self {
+        self.time = time;
+        self
+    }
+
+    /// Returns a ray leaving `origin` at the same time, like a reflected or a shadow ray.
+    pub fn spawn(&self, origin: Point3, dir: Vector3) -> Self {
+        Self::new(origin, dir).with_time(self.time)
     }
 
     pub fn distance_to(&self, point: Point3) -> f64 {
@@ -21,31 +37,31 @@ impl Ray {
     }
 
     pub fn normalize(&self) -> Self {
-        Ray::new(self.origin, self.dir.normalize())
+        self.spawn(self.origin, self.dir.normalize())
     }
 }
 
 impl Transformable for Ray {
     fn translate(self, translation: Vector3) -> Self {
-        Self::new(
+        self.spawn(
             self.origin.translate(translation),
             self.dir.translate(translation),
         )
     }
 
     fn rotate(self, axis: Axis, degrees: f64) -> Self {
-        Self::new(
+        self.spawn(
             self.origin.rotate(axis, degrees),
             self.dir.rotate(axis, degrees),
         )
     }
 
     fn scale(self, scale: Vector3) -> Self {
-        Self::new(self.origin.scale(scale), self.dir.scale(scale))
+        self.spawn(self.origin.scale(scale), self.dir.scale(scale))
     }
 
     fn transform(self, transform: Matrix4) -> Self {
-        Self::new(
+        self.spawn(
             self.origin.transform(transform),
             self.dir.transform(transform),
         )
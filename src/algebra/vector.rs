@@ -1,4 +1,4 @@
-use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::ops::{Add, Div, Index, Mul, Neg, Sub};
 
 use crate::{entity::Transformable, geometry::Axis};
 
@@ -67,6 +67,26 @@ impl Vector3 {
     pub fn normalize(&self) -> Self {
         *self / self.magnitude()
     }
+
+    /// Mirrors the vector about the normal `n`, pointing away from the surface for a vector
+    /// pointing into it.
+    pub fn reflect(&self, n: &Self) -> Self {
+        *self - 2.0 * self.dot(n) * *n
+    }
+
+    /// Refracts the unit vector through a surface with unit normal `n` facing against it, where
+    /// `eta` is the ratio of the refractive indices of the media it comes from and goes into.
+    /// Returns `None` on total internal reflection.
+    pub fn refract(&self, n: &Self, eta: f64) -> Option<Self> {
+        let cos_i = -self.dot(n);
+        let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+        if sin2_t > 1.0 {
+            return None;
+        }
+
+        let cos_t = (1.0 - sin2_t).sqrt();
+        Some(eta * *self + (eta * cos_i - cos_t) * *n)
+    }
 }
 
 impl Add for Vector3 {
@@ -125,6 +145,18 @@ impl Div<Vector3> for f64 {
     }
 }
 
+impl Index<Axis> for Vector3 {
+    type Output = f64;
+
+    fn index(&self, axis: Axis) -> &Self::Output {
+        match axis {
+            Axis::X => &self.x,
+            Axis::Y => &self.y,
+            Axis::Z => &self.z,
+        }
+    }
+}
+
 impl Transformable for Vector3 {
     fn translate(self, translation: Vector3) -> Self {
         Matrix4::translation(translation) * self
@@ -166,6 +198,29 @@ impl From<Vector3> for Vector4 {
 mod tests {
     use super::Vector3;
 
+    #[test]
+    fn reflect() {
+        let d = Vector3::new(1.0, -1.0, 0.0);
+        assert_eq!(d.reflect(&Vector3::J), Vector3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn refract() {
+        let d = Vector3::new(1.0, -1.0, 0.0).normalize();
+
+        // no bending without a change of medium
+        let t = d.refract(&Vector3::J, 1.0).unwrap();
+        assert!((t - d).magnitude() < 1e-12);
+
+        // bends towards the normal going into a denser medium, obeying Snell's law
+        let t = d.refract(&Vector3::J, 1.0 / 1.5).unwrap();
+        assert!((t.magnitude() - 1.0).abs() < 1e-12);
+        assert!((t.x - d.x / 1.5).abs() < 1e-12);
+
+        // total internal reflection going out at a grazing angle
+        assert_eq!(d.refract(&Vector3::J, 1.5), None);
+    }
+
     #[test]
     fn add() {
         let expected = Vector3::new(1.0, 1.0, 0.0);
@@ -0,0 +1,431 @@
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use crate::{
+    algebra::{Point3, Vector3},
+    color::Color,
+    light::Light,
+    scene::Scene,
+};
+
+/// Values that can be blended between keyframes.
+pub trait Lerp: Copy {
+    /// Returns the value a fraction `t` of the way from `self` to `other`.
+    fn lerp(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Lerp for f64 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for Vector3 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        *self + (*other - *self) * t
+    }
+}
+
+impl Lerp for Point3 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        *self + (*other - *self) * t
+    }
+}
+
+impl Lerp for Color {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        *self + (*other - *self) * t
+    }
+}
+
+/// How a value moves from a keyframe to the next one.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Interpolation {
+    /// At constant speed.
+    #[default]
+    Linear,
+    /// Along the cubic Bézier curve from the value of the keyframe to the value of the next
+    /// one, with the out handle of the keyframe and the in handle of the next one as control
+    /// points. Handles at the values of the keyframes ease out of the first one and into the
+    /// next.
+    Bezier,
+    /// Holding the value until the next keyframe.
+    Step,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe<T> {
+    /// Time of the keyframe, in seconds.
+    pub time: f64,
+    pub value: T,
+    /// Interpolation towards the next keyframe.
+    pub interpolation: Interpolation,
+    /// Control points of the Bézier curves before and after the keyframe, placed a third of
+    /// the way in time to the neighboring keyframes.
+    pub in_handle: T,
+    pub out_handle: T,
+}
+
+/// Keyframes of a single animated value, kept sorted by time.
+///
+/// Before the first keyframe and after the last one the value holds still.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track<T> {
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T: Lerp> Track<T> {
+    pub fn new() -> Self {
+        Self {
+            keyframes: Vec::new(),
+        }
+    }
+
+    /// Adds a keyframe, replacing any other one at the same time. Its Bézier handles are at
+    /// its value.
+    pub fn with_key(self, time: f64, value: T, interpolation: Interpolation) -> Self {
+        self.with_keyframe(Keyframe {
+            time,
+            value,
+            interpolation,
+            in_handle: value,
+            out_handle: value,
+        })
+    }
+
+    /// Adds a keyframe with Bézier interpolation towards the next one, shaping the curves
+    /// around it with the given handles.
+    pub fn with_bezier_key(self, time: f64, value: T, in_handle: T, out_handle: T) -> Self {
+        self.with_keyframe(Keyframe {
+            time,
+            value,
+            interpolation: Interpolation::Bezier,
+            in_handle,
+            out_handle,
+        })
+    }
+
+    /// Adds a keyframe, replacing any other one at the same time.
+    pub fn with_keyframe(mut self, keyframe: Keyframe<T>) -> Self {
+        self.keyframes.retain(|k| k.time != keyframe.time);
+        let index = self.keyframes.partition_point(|k| k.time < keyframe.time);
+        self.keyframes.insert(index, keyframe);
+        self
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe<T>] {
+        &self.keyframes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    /// Returns the value at `time`, or `None` if the track has no keyframes.
+    pub fn value_at(&self, time: f64) -> Option<T> {
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        if next == 0 {
+            return self.keyframes.first().map(|k| k.value);
+        }
+
+        let start = &self.keyframes[next - 1];
+        let end = match self.keyframes.get(next) {
+            Some(end) => end,
+            None => return Some(start.value),
+        };
+
+        let t = (time - start.time) / (end.time - start.time);
+        let value = match start.interpolation {
+            Interpolation::Linear => start.value.lerp(&end.value, t),
+            Interpolation::Bezier => {
+                // de Casteljau's construction
+                let a = start.value.lerp(&start.out_handle, t);
+                let b = start.out_handle.lerp(&end.in_handle, t);
+                let c = end.in_handle.lerp(&end.value, t);
+                a.lerp(&b, t).lerp(&b.lerp(&c, t), t)
+            }
+            Interpolation::Step => start.value,
+        };
+        Some(value)
+    }
+}
+
+impl<T: Lerp> Default for Track<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tracks of the pose of an entity, replacing the translation, rotation and scale it was
+/// built with while they have keyframes. Rotations are angles in degrees about the x, y and z
+/// axes, applied in that order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EntityTracks {
+    pub translation: Track<Vector3>,
+    pub rotation: Track<Vector3>,
+    pub scale: Track<Vector3>,
+}
+
+impl EntityTracks {
+    fn is_empty(&self) -> bool {
+        self.translation.is_empty() && self.rotation.is_empty() && self.scale.is_empty()
+    }
+}
+
+/// Tracks of the parameters of a point light, with the color setting both its diffuse and
+/// specular colors.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PointLightTracks {
+    pub position: Track<Point3>,
+    pub intensity: Track<f64>,
+    pub color: Track<Color>,
+}
+
+/// Keyframe animation of the camera, entities and point lights of a scene.
+///
+/// Entities and lights are referred to by their index in the scene, in the order they were
+/// added. Anything without keyframes is left as it is in the scene.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Animation {
+    pub camera_position: Track<Point3>,
+    pub camera_look_at: Track<Point3>,
+    entities: Vec<(usize, EntityTracks)>,
+    point_lights: Vec<(usize, PointLightTracks)>,
+}
+
+impl Animation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_camera(mut self, position: Track<Point3>, look_at: Track<Point3>) -> Self {
+        self.camera_position = position;
+        self.camera_look_at = look_at;
+        self
+    }
+
+    pub fn with_entity(mut self, index: usize, tracks: EntityTracks) -> Self {
+        self.entities.push((index, tracks));
+        self
+    }
+
+    /// Animates the light at `index` in `Scene::lights`, which has to be a point light.
+    pub fn with_point_light(mut self, index: usize, tracks: PointLightTracks) -> Self {
+        self.point_lights.push((index, tracks));
+        self
+    }
+
+    /// Poses the scene at `time`, in seconds.
+    ///
+    /// Only moving entities invalidate the acceleration structure over the entities, and the
+    /// ones inside meshes are always kept, as entities move as a whole. The scene is left
+    /// untouched if an index does not match an entity or a point light.
+    pub fn apply(&self, scene: &mut Scene, time: f64) -> Result<(), String> {
+        for (index, _) in &self.entities {
+            if *index >= scene.entities.len() {
+                return Err(format!("entity {}: no such entity", index));
+            }
+        }
+        for (index, _) in &self.point_lights {
+            if !matches!(scene.lights.get(*index), Some(Light::Point(_))) {
+                return Err(format!("light {}: not a point light", index));
+            }
+        }
+
+        if let Some(position) = self.camera_position.value_at(time) {
+            scene.camera.position = position;
+        }
+        if let Some(target) = self.camera_look_at.value_at(time) {
+            scene.camera.look_at(target);
+        }
+
+        for (index, tracks) in &self.entities {
+            if tracks.is_empty() {
+                continue;
+            }
+
+            scene.update_entity(*index, |entity| {
+                entity.set_pose(
+                    tracks.translation.value_at(time),
+                    tracks.rotation.value_at(time),
+                    tracks.scale.value_at(time),
+                )
+            });
+        }
+
+        for (index, tracks) in &self.point_lights {
+            // the indices were checked to be point lights above
+            if let Light::Point(light) = &mut scene.lights[*index] {
+                if let Some(position) = tracks.position.value_at(time) {
+                    light.position = position;
+                }
+                if let Some(intensity) = tracks.intensity.value_at(time) {
+                    light.intensity = intensity;
+                }
+                if let Some(color) = tracks.color.value_at(time) {
+                    light.diffuse = color;
+                    light.specular = color;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Renders the frames in `frames` at `fps` frames per second, frame `n` showing the scene
+    /// at `n / fps` seconds, and saves them in `directory` as `frame_0001.png` and so on.
+    pub fn render_sequence(
+        &self,
+        scene: &mut Scene,
+        frames: RangeInclusive<u32>,
+        fps: f64,
+        directory: impl AsRef<Path>,
+    ) -> Result<(), String> {
+        if fps.is_nan() || fps <= 0.0 {
+            return Err(format!("fps: expected a positive number, got {}", fps));
+        }
+        let directory = directory.as_ref();
+        std::fs::create_dir_all(directory)
+            .map_err(|e| format!("{}: {}", directory.display(), e))?;
+
+        for frame in frames {
+            self.apply(scene, frame as f64 / fps)?;
+
+            let path = directory.join(format!("frame_{:04}.png", frame));
+            scene
+                .render()
+                .save(&path)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        algebra::Ray,
+        camera::Camera,
+        entity::Entity,
+        geometry::{Intersect, Sphere},
+        light::PointLight,
+    };
+
+    #[test]
+    fn interpolation() {
+        let track = Track::new()
+            .with_key(2.0, 10.0, Interpolation::Bezier)
+            .with_key(0.0, 0.0, Interpolation::Linear)
+            .with_key(3.0, 20.0, Interpolation::Step)
+            .with_key(4.0, 30.0, Interpolation::Linear);
+
+        assert_eq!(Track::<f64>::new().value_at(1.0), None);
+        assert_eq!(track.keyframes()[1].time, 2.0);
+        assert_eq!(track.value_at(-1.0), Some(0.0));
+        assert_eq!(track.value_at(1.0), Some(5.0));
+        assert_eq!(track.value_at(2.0), Some(10.0));
+        // the Bézier segment eases in and out around its midpoint
+        assert_eq!(track.value_at(2.5), Some(15.0));
+        assert!(track.value_at(2.1).unwrap() < 11.0);
+        assert!(track.value_at(2.9).unwrap() > 19.0);
+        assert_eq!(track.value_at(3.9), Some(20.0));
+        assert_eq!(track.value_at(5.0), Some(30.0));
+
+        // handles a third of the way along the segment make the curve a straight line, and
+        // moving them shapes it
+        let track = Track::new()
+            .with_bezier_key(0.0, 0.0, 0.0, 1.0)
+            .with_bezier_key(1.0, 3.0, 2.0, 3.0);
+        assert!((track.value_at(0.25).unwrap() - 0.75).abs() < 1e-12);
+        let track = track.with_bezier_key(0.0, 0.0, 0.0, 3.0);
+        assert_eq!(track.value_at(0.5), Some(2.25));
+    }
+
+    #[test]
+    fn animate_scene() {
+        let mut scene = Scene::new(4, 4, Camera::new(Point3::O), Color::BLACK);
+        scene.add_entity(Entity::from(Sphere::default()));
+        scene.add_light(PointLight::new(
+            Point3::O,
+            Color::BLACK,
+            Color::WHITE,
+            Color::WHITE,
+            1.0,
+        ));
+
+        let animation = Animation::new()
+            .with_camera(
+                Track::new().with_key(0.0, Point3::new(0.0, 0.0, -10.0), Interpolation::Linear),
+                Track::new().with_key(0.0, Point3::O, Interpolation::Linear),
+            )
+            .with_entity(
+                0,
+                EntityTracks {
+                    translation: Track::new()
+                        .with_key(0.0, Vector3::ZERO, Interpolation::Linear)
+                        .with_key(1.0, Vector3::new(4.0, 0.0, 0.0), Interpolation::Linear),
+                    ..EntityTracks::default()
+                },
+            )
+            .with_point_light(
+                0,
+                PointLightTracks {
+                    intensity: Track::new().with_key(0.0, 5.0, Interpolation::Linear),
+                    ..PointLightTracks::default()
+                },
+            );
+        animation.apply(&mut scene, 0.5).unwrap();
+
+        assert_eq!(scene.camera.position, Point3::new(0.0, 0.0, -10.0));
+        assert_eq!(scene.camera.view, Vector3::K);
+        match &scene.lights[0] {
+            Light::Point(light) => assert_eq!(light.intensity, 5.0),
+            light => panic!("{:?}", light),
+        }
+
+        let ray = Ray::new(Point3::new(2.0, 0.0, -10.0), Vector3::K);
+        assert!(scene.entities[0].intersect(&ray).is_some());
+        assert!(scene.closest_intersection(&ray).is_some());
+
+        let error = Animation::new()
+            .with_camera(
+                Track::new().with_key(0.0, Point3::O, Interpolation::Linear),
+                Track::new(),
+            )
+            .with_point_light(1, PointLightTracks::default())
+            .apply(&mut scene, 0.0);
+        assert_eq!(error, Err("light 1: not a point light".to_string()));
+        assert_eq!(scene.camera.position, Point3::new(0.0, 0.0, -10.0));
+
+        let error = animation.render_sequence(&mut scene, 0..=1, 0.0, "frames");
+        assert_eq!(
+            error,
+            Err("fps: expected a positive number, got 0".to_string())
+        );
+    }
+
+    #[test]
+    fn scale_from_zero() {
+        let mut scene = Scene::new(4, 4, Camera::new(Point3::O), Color::BLACK);
+        scene.add_entity(Entity::from(Sphere::default()));
+
+        let animation = Animation::new().with_entity(
+            0,
+            EntityTracks {
+                scale: Track::new()
+                    .with_key(0.0, Vector3::ZERO, Interpolation::Linear)
+                    .with_key(1.0, Vector3::new(2.0, 2.0, 2.0), Interpolation::Linear),
+                ..EntityTracks::default()
+            },
+        );
+        let ray = Ray::new(Point3::new(0.0, 0.0, -10.0), Vector3::K);
+
+        animation.apply(&mut scene, 0.0).unwrap();
+        assert!(scene.closest_intersection(&ray).is_none());
+
+        animation.apply(&mut scene, 0.5).unwrap();
+        assert!(scene.closest_intersection(&ray).is_some());
+    }
+}
@@ -0,0 +1,280 @@
+use std::f64::consts::PI;
+
+use crate::{
+    algebra::{Point2, Point3, Vector3},
+    color::Color,
+    geometry::{Cube, Intersection, Textured},
+    texture::{ColoredTexture, Image, TextureCoordinate},
+};
+
+/// What the rays leaving the scene see.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Background {
+    Color(Color),
+    Environment(Environment),
+}
+
+impl Background {
+    /// Returns the radiance arriving from infinitely far away along the unit direction `dir`.
+    pub fn radiance(&self, dir: &Vector3) -> Color {
+        match self {
+            Self::Color(c) => *c,
+            Self::Environment(env) => env.radiance(dir),
+        }
+    }
+
+    /// Returns the environment if it should light the scene.
+    pub fn light(&self) -> Option<&Environment> {
+        match self {
+            Self::Environment(env) if env.light_samples > 0 => Some(env),
+            _ => None,
+        }
+    }
+}
+
+impl From<Color> for Background {
+    fn from(c: Color) -> Self {
+        Self::Color(c)
+    }
+}
+
+impl From<Environment> for Background {
+    fn from(env: Environment) -> Self {
+        Self::Environment(env)
+    }
+}
+
+/// Image wrapped around the scene, looked up by direction.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnvironmentMap {
+    /// Equirectangular image, with the longitude along the width, centered on the positive z
+    /// axis, and the latitude along the height, with the top row looking straight up.
+    LatLong(Image),
+    /// Cube map unfolded into a horizontal cross, laid out like the textures of a `Cube`.
+    CrossCubeMap(Image),
+}
+
+impl EnvironmentMap {
+    fn color_at(&self, dir: &Vector3) -> Color {
+        let uv = match self {
+            Self::LatLong(_) => {
+                let (u, v) = lat_long(dir);
+                Point2::new(u, v)
+            }
+            Self::CrossCubeMap(_) => {
+                // look the direction up on the faces of a unit cube around the origin
+                let max = dir.x.abs().max(dir.y.abs()).max(dir.z.abs());
+                let p = Point3::O + *dir * (0.5 / max);
+                Cube::default().to_texture_space(&Intersection::new(0.0, p, -*dir))
+            }
+        };
+
+        let image = match self {
+            Self::LatLong(image) | Self::CrossCubeMap(image) => image,
+        };
+        image.color_at(TextureCoordinate::new(uv.x, uv.y))
+    }
+}
+
+// resolution of the grid the environment is importance sampled with
+const GRID_WIDTH: usize = 256;
+const GRID_HEIGHT: usize = 128;
+
+/// Environment map surrounding the scene, optionally lighting it through importance sampled
+/// image based lighting.
+///
+/// Directions are sampled proportionally to the luminance of the map, over a lat-long grid, so
+/// that small bright areas like the sun get most of the samples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Environment {
+    pub map: EnvironmentMap,
+    /// Factor the radiance of the map is scaled by.
+    pub intensity: f64,
+    /// Number of samples taken towards the environment at every shading point, 0 keeps it from
+    /// being used as a light.
+    pub light_samples: u32,
+    // running sum of the sampling weights of the grid cells, row by row, normalized to end at 1
+    cdf: Vec<f64>,
+}
+
+impl Environment {
+    pub fn new(map: EnvironmentMap) -> Self {
+        let mut total = 0.0;
+        let mut cdf = Vec::with_capacity(GRID_WIDTH * GRID_HEIGHT);
+        for j in 0..GRID_HEIGHT {
+            // cells near the poles cover a smaller solid angle
+            let theta = PI * (j as f64 + 0.5) / GRID_HEIGHT as f64;
+            for i in 0..GRID_WIDTH {
+                let u = (i as f64 + 0.5) / GRID_WIDTH as f64;
+                let dir = lat_long_direction(u, 1.0 - (j as f64 + 0.5) / GRID_HEIGHT as f64);
+                total += map.color_at(&dir).luminance().max(0.0) * theta.sin();
+                cdf.push(total);
+            }
+        }
+
+        if total > 0.0 {
+            cdf.iter_mut().for_each(|x| *x /= total);
+        } else {
+            // a black map, fall back to sampling the sphere uniformly
+            let n = cdf.len() as f64;
+            cdf.iter_mut()
+                .enumerate()
+                .for_each(|(i, x)| *x = (i + 1) as f64 / n);
+        }
+
+        Self {
+            map,
+            intensity: 1.0,
+            light_samples: 0,
+            cdf,
+        }
+    }
+
+    pub fn with_intensity(mut self, intensity: f64) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn with_light_samples(mut self, light_samples: u32) -> Self {
+        self.light_samples = light_samples;
+        self
+    }
+
+    pub fn radiance(&self, dir: &Vector3) -> Color {
+        self.map.color_at(dir) * self.intensity
+    }
+
+    /// Maps a position in the unit square to a direction distributed according to the
+    /// brightness of the map, returning it with its density over the sphere of directions.
+    pub fn sample(&self, uv: &Point2) -> (Vector3, f64) {
+        let cell = self
+            .cdf
+            .partition_point(|&x| x < uv.x)
+            .min(self.cdf.len() - 1);
+        let start = if cell == 0 { 0.0 } else { self.cdf[cell - 1] };
+        let du = ((uv.x - start) / (self.cdf[cell] - start)).clamp(0.0, 1.0);
+
+        let (i, j) = (cell % GRID_WIDTH, cell / GRID_WIDTH);
+        let u = (i as f64 + du) / GRID_WIDTH as f64;
+        let v = 1.0 - (j as f64 + uv.y) / GRID_HEIGHT as f64;
+        let dir = lat_long_direction(u, v);
+
+        (dir, self.pdf(&dir))
+    }
+
+    /// Density of [`sample`](Self::sample) for the unit direction `dir`.
+    pub fn pdf(&self, dir: &Vector3) -> f64 {
+        let (u, v) = lat_long(dir);
+        let i = ((u * GRID_WIDTH as f64) as usize).min(GRID_WIDTH - 1);
+        let j = (((1.0 - v) * GRID_HEIGHT as f64) as usize).min(GRID_HEIGHT - 1);
+        let cell = j * GRID_WIDTH + i;
+        let p = self.cdf[cell] - if cell == 0 { 0.0 } else { self.cdf[cell - 1] };
+
+        let sin_theta = (1.0 - dir.y * dir.y).max(0.0).sqrt();
+        if sin_theta == 0.0 {
+            return 0.0;
+        }
+
+        // a cell spans 2 PI / GRID_WIDTH by PI / GRID_HEIGHT radians
+        p * (GRID_WIDTH * GRID_HEIGHT) as f64 / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+/// Returns the lat-long texture coordinate of a unit direction.
+fn lat_long(dir: &Vector3) -> (f64, f64) {
+    let phi = dir.x.atan2(dir.z);
+    let theta = dir.y.clamp(-1.0, 1.0).acos();
+    (0.5 + phi / (2.0 * PI), 1.0 - theta / PI)
+}
+
+fn lat_long_direction(u: f64, v: f64) -> Vector3 {
+    let phi = (u - 0.5) * 2.0 * PI;
+    let theta = (1.0 - v) * PI;
+    Vector3::new(
+        theta.sin() * phi.sin(),
+        theta.cos(),
+        theta.sin() * phi.cos(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, GenericImage, Rgba};
+
+    use super::*;
+    use crate::sampling;
+
+    // lat-long map that is black apart from a bright patch around the positive x axis
+    fn sun_map() -> Environment {
+        let mut image = DynamicImage::new_rgb8(64, 32);
+        for y in 0..32 {
+            for x in 0..64 {
+                let sun = (44..52).contains(&x) && (12..20).contains(&y);
+                let c = if sun { 255 } else { 10 };
+                image.put_pixel(x, y, Rgba([c, c, c, 255]));
+            }
+        }
+        Environment::new(EnvironmentMap::LatLong(Image::from(image)))
+    }
+
+    #[test]
+    fn lat_long_round_trip() {
+        for dir in [
+            Vector3::I,
+            -Vector3::K,
+            Vector3::new(1.0, 2.0, -3.0).normalize(),
+        ] {
+            let (u, v) = lat_long(&dir);
+            assert!((lat_long_direction(u, v) - dir).magnitude() < 1e-9);
+        }
+        assert_eq!(lat_long(&Vector3::K), (0.5, 0.5));
+    }
+
+    #[test]
+    fn background_lookup() {
+        let env = sun_map().with_intensity(2.0);
+        assert_eq!(env.radiance(&Vector3::I), Color::WHITE * 2.0);
+        assert_eq!(
+            env.radiance(&-Vector3::I),
+            Color::from(Rgba([10, 10, 10, 255])) * 2.0
+        );
+
+        let background = Background::from(Color::RED);
+        assert_eq!(background.radiance(&Vector3::J), Color::RED);
+        assert_eq!(background.light(), None);
+        assert_eq!(Background::from(env.clone()).light(), None);
+        assert!(Background::from(env.with_light_samples(4))
+            .light()
+            .is_some());
+    }
+
+    #[test]
+    fn importance_sampling() {
+        let env = sun_map();
+        let samples = sampling::stratified_2d(4096);
+
+        let towards_sun = samples
+            .iter()
+            .filter(|uv| env.sample(uv).0.dot(&Vector3::I) > 0.9)
+            .count();
+        assert!(towards_sun > 2048, "{}", towards_sun);
+
+        // the pdf integrates to 1 over the sphere, estimated with uniform directions
+        let n = 20000;
+        let integral = (0..n)
+            .map(|_| {
+                let d = sampling::cosine_power_direction(&Vector3::J, 0.0);
+                let d = if rand::random::<bool>() { d } else { -d };
+                env.pdf(&d) * 4.0 * PI
+            })
+            .sum::<f64>()
+            / n as f64;
+        assert!((integral - 1.0).abs() < 0.05, "{}", integral);
+
+        for uv in samples.iter().take(100) {
+            let (dir, pdf) = env.sample(uv);
+            assert!((dir.magnitude() - 1.0).abs() < 1e-9);
+            assert!(pdf > 0.0);
+        }
+    }
+}
@@ -1,11 +1,48 @@
-use crate::algebra::{EPSILON, Point3, Vector3};
+use std::f64::consts::{FRAC_PI_2, PI};
 
-#[derive(Default, Debug, Clone, PartialEq)]
+use crate::algebra::{Matrix4, Point2, Point3, Ray, Vector3, EPSILON};
+use crate::entity::Transformable;
+use crate::geometry::Axis;
+use crate::sampling;
+
+/// How the camera maps the pixels of the image to the directions it looks in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// Pinhole projection, with the horizontal field of view in radians.
+    Perspective { fov: f64 },
+    /// Parallel rays along the view vector, through a view `height` world units high.
+    Orthographic { height: f64 },
+    /// Equidistant fisheye, with the angle to the view vector growing linearly with the
+    /// distance to the center of the image, up to half of `fov` radians at the sides.
+    Fisheye { fov: f64 },
+    /// Full 360° panorama, with the longitude along the width and the latitude along the
+    /// height of the image.
+    Equirectangular,
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Self::Perspective { fov: FRAC_PI_2 }
+    }
+}
+
+/// Thin-lens camera, a pinhole camera when its aperture is 0.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Camera {
     pub position: Point3,
     pub view: Vector3,
     pub up: Vector3,
     pub side: Vector3,
+    pub projection: Projection,
+    /// Radius of the lens, larger apertures blur more what is out of focus.
+    pub aperture: f64,
+    /// Distance along the view vector of the plane in focus.
+    pub focus_distance: f64,
+    /// Time the shutter opens at, moving entities go from their start to their end between
+    /// times 0 and 1.
+    pub shutter_open: f64,
+    /// Time the shutter closes at, nothing is blurred when it is the opening time.
+    pub shutter_close: f64,
 }
 
 impl Camera {
@@ -15,9 +52,40 @@ impl Camera {
             view: Vector3::K,
             up: Vector3::J,
             side: Vector3::J.cross(&Vector3::K),
+            projection: Projection::default(),
+            aperture: 0.0,
+            focus_distance: 1.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
         }
     }
 
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    pub fn with_aperture(mut self, aperture: f64) -> Self {
+        self.aperture = aperture;
+        self
+    }
+
+    pub fn with_focus_distance(mut self, focus_distance: f64) -> Self {
+        self.focus_distance = focus_distance;
+        self
+    }
+
+    pub fn with_shutter(mut self, open: f64, close: f64) -> Self {
+        self.shutter_open = open;
+        self.shutter_close = close;
+        self
+    }
+
+    /// Maps a position between 0 and 1 to a time the shutter is open at.
+    pub fn time(&self, u: f64) -> f64 {
+        self.shutter_open + (self.shutter_close - self.shutter_open) * u
+    }
+
     pub fn look_at(&mut self, point: Point3) {
         // view vector
         self.view = (point - self.position).normalize();
@@ -32,4 +100,186 @@ impl Camera {
         // up vector
         self.up = self.view.cross(&self.side).normalize();
     }
+
+    /// Puts the point in focus, without changing where the camera looks.
+    pub fn focus_on(&mut self, point: Point3) {
+        self.focus_distance = (point - self.position).dot(&self.view);
+    }
+
+    /// Returns the ray leaving the center of the lens through the position `(x, y)` of an
+    /// image of `width` by `height` pixels, in pixels from the top left corner.
+    pub fn ray(&self, x: f64, y: f64, width: u32, height: u32) -> Ray {
+        // integer coordinates are the centers of the pixels
+        let (cx, cy) = ((width / 2) as f64, (height / 2) as f64);
+        match self.projection {
+            Projection::Perspective { fov } => {
+                let d = (width as f64 / 2.0) / (fov / 2.0).tan();
+                let raw = d * self.view + (x - cx) * self.side + (cy - y) * self.up;
+                Ray::new(self.position, raw)
+            }
+            Projection::Orthographic {
+                height: view_height,
+            } => {
+                let scale = view_height / height as f64;
+                let origin =
+                    self.position + (x - cx) * scale * self.side + (cy - y) * scale * self.up;
+                Ray::new(origin, self.view)
+            }
+            Projection::Fisheye { fov } => {
+                let (dx, dy) = (x - cx, cy - y);
+                let r = (dx * dx + dy * dy).sqrt();
+                if r == 0.0 {
+                    return Ray::new(self.position, self.view);
+                }
+
+                let theta = r / (width as f64 / 2.0) * fov / 2.0;
+                let radial = (dx / r) * self.side + (dy / r) * self.up;
+                Ray::new(
+                    self.position,
+                    theta.cos() * self.view + theta.sin() * radial,
+                )
+            }
+            Projection::Equirectangular => {
+                let phi = (x - cx) / width as f64 * 2.0 * PI;
+                let theta = (y - cy) / height as f64 * PI + FRAC_PI_2;
+                let dir = theta.sin() * phi.sin() * self.side
+                    + theta.cos() * self.up
+                    + theta.sin() * phi.cos() * self.view;
+                Ray::new(self.position, dir)
+            }
+        }
+    }
+
+    /// Turns a ray leaving the center of the lens into the ray leaving the lens at `lens`, a
+    /// position in the unit square, that converges with it on the plane in focus.
+    ///
+    /// Rays that don't look ahead of the camera, which only wide projections have, never
+    /// reach the plane and stay sharp.
+    pub fn lens_ray(&self, ray: &Ray, lens: &Point2) -> Ray {
+        let cos = ray.dir.dot(&self.view);
+        if self.aperture <= 0.0 || cos <= EPSILON {
+            return *ray;
+        }
+
+        let focus = ray.origin + ray.dir * (self.focus_distance / cos);
+        let disk = sampling::concentric_disk(lens);
+        let origin = ray.origin + self.aperture * (disk.x * self.side + disk.y * self.up);
+        Ray::new(origin, focus - origin)
+    }
+}
+
+/// A camera at the origin looking along the z axis, like `Camera::new(Point3::O)`.
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new(Point3::O)
+    }
+}
+
+/// Cameras are moved as a whole, keeping their orientation vectors of unit length.
+impl Transformable for Camera {
+    fn translate(self, translation: Vector3) -> Self {
+        self.transform(Matrix4::translation(translation))
+    }
+
+    fn rotate(self, axis: Axis, degrees: f64) -> Self {
+        self.transform(Matrix4::rotation(axis, degrees))
+    }
+
+    fn scale(self, scale: Vector3) -> Self {
+        self.transform(Matrix4::scaling(scale))
+    }
+
+    fn transform(self, transform: Matrix4) -> Self {
+        Self {
+            position: self.position.transform(transform),
+            view: self.view.transform(transform).normalize(),
+            up: self.up.transform(transform).normalize(),
+            side: self.side.transform(transform).normalize(),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vector3, b: Vector3) {
+        assert!((a - b).magnitude() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn projections() {
+        let mut camera = Camera::new(Point3::new(0.0, 0.0, -10.0));
+        camera.look_at(Point3::new(1.0, 0.0, -10.0));
+        let center = |camera: &Camera| camera.ray(40.0, 30.0, 80, 60).normalize();
+
+        // every projection looks ahead at the center of the image
+        for projection in [
+            Projection::Perspective { fov: 1.0 },
+            Projection::Orthographic { height: 6.0 },
+            Projection::Fisheye { fov: PI },
+            Projection::Equirectangular,
+        ] {
+            let camera = camera.clone().with_projection(projection);
+            let ray = center(&camera);
+            assert_eq!(ray.origin, camera.position);
+            assert_close(ray.dir, Vector3::I);
+        }
+
+        let camera = camera.with_projection(Projection::Orthographic { height: 6.0 });
+        let ray = camera.ray(0.0, 0.0, 80, 60);
+        assert_eq!(ray.dir, Vector3::I);
+        assert_close(ray.origin - camera.position, Vector3::new(0.0, 3.0, 4.0));
+
+        // a 180° fisheye looks sideways at the sides of the image
+        let camera = camera.with_projection(Projection::Fisheye { fov: PI });
+        assert_close(camera.ray(80.0, 30.0, 80, 60).dir, camera.side);
+        assert_close(camera.ray(40.0, 10.0, 80, 60).dir.normalize(), {
+            let a = PI / 2.0 * 20.0 / 40.0;
+            a.cos() * camera.view + a.sin() * camera.up
+        });
+
+        // a panorama covers the whole sphere
+        let camera = camera.with_projection(Projection::Equirectangular);
+        assert_close(camera.ray(40.0, 0.0, 80, 60).dir, camera.up);
+        assert_close(camera.ray(0.0, 30.0, 80, 60).dir, -camera.view);
+        assert_close(camera.ray(60.0, 30.0, 80, 60).dir, camera.side);
+    }
+
+    #[test]
+    fn pinhole_by_default() {
+        let camera = Camera::new(Point3::O);
+        let ray = Ray::new(Point3::O, Vector3::new(0.5, 0.2, 1.0));
+        assert_eq!(camera.lens_ray(&ray, &Point2::new(0.1, 0.9)), ray);
+
+        // and in focus one unit away when given an aperture
+        let camera = Camera::default().with_aperture(0.5);
+        let lens_ray = camera.lens_ray(&ray, &Point2::new(0.1, 0.9));
+        assert!((lens_ray.origin + lens_ray.dir - (ray.origin + ray.dir)).magnitude() < EPSILON);
+    }
+
+    #[test]
+    fn lens_rays_converge_in_focus() {
+        let mut camera = Camera::new(Point3::new(1.0, 2.0, 3.0)).with_aperture(0.5);
+        camera.look_at(Point3::new(1.0, 2.0, 10.0));
+        camera.focus_on(Point3::new(4.0, 0.0, 8.0));
+        assert!((camera.focus_distance - 5.0).abs() < EPSILON);
+
+        let ray = Ray::new(camera.position, Vector3::new(0.3, -0.1, 1.0));
+        let focus = ray.origin + ray.dir * 5.0;
+        for lens in [
+            Point2::new(0.0, 0.0),
+            Point2::new(0.9, 0.2),
+            Point2::new(0.5, 1.0),
+        ] {
+            let lens_ray = camera.lens_ray(&ray, &lens);
+            assert!((lens_ray.origin - camera.position).magnitude() <= 0.5 + EPSILON);
+            assert!((lens_ray.origin + lens_ray.dir - focus).magnitude() < EPSILON);
+        }
+
+        // the center of the lens acts as the pinhole
+        let center = camera.lens_ray(&ray, &Point2::new(0.5, 0.5));
+        assert_eq!(center.origin, camera.position);
+    }
 }
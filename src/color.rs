@@ -5,6 +5,8 @@ use std::{
     ops::{Add, Mul, Sub},
 };
 
+/// Linear RGB color. Components are not clamped, so that radiance can exceed 1 while shading,
+/// clamping only happens when converting to an 8 bit pixel.
 #[derive(Default, Debug, Clone, Copy, PartialEq)]
 pub struct Color {
     pub r: f64,
@@ -40,16 +42,50 @@ impl Color {
     };
 
     pub fn new(r: f64, g: f64, b: f64) -> Self {
-        Self {
-            r: r.clamp(0.0, 1.0),
-            g: g.clamp(0.0, 1.0),
-            b: b.clamp(0.0, 1.0),
-        }
+        Self { r, g, b }
     }
 
     pub fn random() -> Self {
         Self::new(random::<f64>(), random::<f64>(), random::<f64>())
     }
+
+    /// Clamps every component to [0, 1].
+    pub fn clamp(&self) -> Self {
+        Self::new(
+            self.r.clamp(0.0, 1.0),
+            self.g.clamp(0.0, 1.0),
+            self.b.clamp(0.0, 1.0),
+        )
+    }
+
+    /// Relative luminance of the linear color, using the Rec. 709 weights.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    /// Encodes the linear color with the sRGB transfer function.
+    pub fn to_srgb(self) -> Self {
+        fn encode(x: f64) -> f64 {
+            if x <= 0.003_130_8 {
+                12.92 * x
+            } else {
+                1.055 * x.powf(1.0 / 2.4) - 0.055
+            }
+        }
+        Self::new(encode(self.r), encode(self.g), encode(self.b))
+    }
+
+    /// Decodes an sRGB encoded color back to linear.
+    pub fn to_linear(self) -> Self {
+        fn decode(x: f64) -> f64 {
+            if x <= 0.040_45 {
+                x / 12.92
+            } else {
+                ((x + 0.055) / 1.055).powf(2.4)
+            }
+        }
+        Self::new(decode(self.r), decode(self.g), decode(self.b))
+    }
 }
 
 impl From<Color> for Rgba<u8> {
@@ -93,8 +129,7 @@ impl Mul<f64> for Color {
     type Output = Self;
 
     fn mul(self, rhs: f64) -> Self::Output {
-        let val = rhs.clamp(0.0, 1.0);
-        Self::new(self.r * val, self.g * val, self.b * val)
+        Self::new(self.r * rhs, self.g * rhs, self.b * rhs)
     }
 }
 
@@ -102,8 +137,7 @@ impl Mul<Color> for f64 {
     type Output = Color;
 
     fn mul(self, rhs: Color) -> Self::Output {
-        let val = self.clamp(0.0, 1.0);
-        Color::new(val * rhs.r, val * rhs.g, val * rhs.b)
+        Color::new(self * rhs.r, self * rhs.g, self * rhs.b)
     }
 }
 
@@ -120,3 +154,31 @@ impl Sum for Color {
         iter.fold(Color::BLACK, |a, e| a + e)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unclamped_arithmetic() {
+        let c = Color::WHITE * 0.75 + Color::WHITE * 0.75;
+        assert_eq!(c, Color::new(1.5, 1.5, 1.5));
+        assert_eq!(2.0 * c, Color::new(3.0, 3.0, 3.0));
+        assert_eq!(c.clamp(), Color::WHITE);
+    }
+
+    #[test]
+    fn to_rgba() {
+        let pixel: Rgba<u8> = Color::new(2.0, 0.5, -1.0).into();
+        assert_eq!(pixel, Rgba([255, 127, 0, 255]));
+    }
+
+    #[test]
+    fn srgb_round_trip() {
+        for x in [0.0, 0.002, 0.18, 0.5, 1.0] {
+            let c = Color::new(x, x, x).to_srgb().to_linear();
+            assert!((c.r - x).abs() < 1e-12);
+        }
+        assert!((Color::WHITE * 0.18).to_srgb().r > 0.46);
+    }
+}
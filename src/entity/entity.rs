@@ -1,23 +1,39 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
 use crate::{
     algebra::{Matrix4, Point2, Point3, Ray, Vector3},
-    geometry::{Axis, Cube, Geometry, Intersect, Intersection, Mesh, Plane, Sphere, Textured},
+    color::Color,
+    geometry::{
+        Axis, Bounded, BoundingBox, Cube, Geometry, Intersect, Intersection, Mesh, Plane, Sphere,
+        Textured,
+    },
+    light::{AreaLight, AreaShape, Light, MeshLight},
     material::Phong,
 };
 
-use super::Transformable;
+use super::{compose, Motion, Transformable};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Entity {
     geometry: Geometry,
     material: Phong,
+    casts_shadows: bool,
 
     translation: Matrix4,
     rotation: Matrix4,
     scaling: Matrix4,
     ad_hoc_transform: Matrix4,
+    motion: Option<Motion>,
+    parent_transform: Matrix4,
 
+    // transform at time 0, and its inverse, `None` when it is singular
     transform: Matrix4,
-    inv_transform: Matrix4,
+    inv_transform: Option<Matrix4>,
+    // inverses of the static parts of the transform, to invert it at any time of a motion
+    // without inverting a whole matrix, `None` when they are singular
+    inv_parent_transform: Option<Matrix4>,
+    inv_ad_hoc_transform: Option<Matrix4>,
 }
 
 impl Entity {
@@ -30,20 +46,206 @@ impl Entity {
         &self.material
     }
 
+    pub fn geometry(&self) -> &Geometry {
+        &self.geometry
+    }
+
+    /// Stops the entity from blocking shadow rays, for example for a sky box enclosing the
+    /// scene.
+    pub fn without_shadows(mut self) -> Self {
+        self.casts_shadows = false;
+        self
+    }
+
+    pub fn casts_shadows(&self) -> bool {
+        self.casts_shadows
+    }
+
+    /// Moves the entity while the camera shutter is open, blurring it in the rendered image.
+    pub fn with_motion(mut self, motion: Motion) -> Self {
+        self.motion = Some(motion);
+        self
+    }
+
+    pub fn motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+
+    /// Returns a light sampling the surface of the entity in world space if its material is
+    /// emissive, so that its glow can light the rest of the scene. Planes are infinite and
+    /// can't be sampled, spheres are assumed to be scaled uniformly, and moving entities light
+    /// the scene from where they are at time 0.
+    ///
+    /// The entity has to be built first.
+    pub fn light(&self) -> Option<Light> {
+        let emission = self.material.emission;
+        if !self.material.is_emissive() {
+            return None;
+        }
+
+        let transform = |triangles: Vec<[Point3; 3]>| {
+            triangles
+                .into_iter()
+                .map(|t| t.map(|p| p.transform(self.transform)))
+                .collect()
+        };
+
+        match &self.geometry {
+            Geometry::Sphere(sphere) => {
+                let center = sphere.center.transform(self.transform);
+                let radius = ((sphere.center + sphere.radius * Vector3::I)
+                    .transform(self.transform)
+                    - center)
+                    .magnitude();
+
+                // a sphere of radiance L is as bright as a point light with an intensity of
+                // L * r^2 in the convention of the other lights
+                Some(
+                    AreaLight::new(
+                        AreaShape::Sphere { center, radius },
+                        Color::BLACK,
+                        emission,
+                        emission,
+                        radius * radius,
+                    )
+                    .into(),
+                )
+            }
+            Geometry::Plane(_) => None,
+            Geometry::Cube(cube) => {
+                Some(MeshLight::new(transform(cube.triangles()), emission).into())
+            }
+            Geometry::Mesh(mesh) => {
+                Some(MeshLight::new(transform(mesh.triangles()), emission).into())
+            }
+        }
+    }
+
     pub fn build(mut self) -> Self {
-        // apply eveything except translation
-        self.transform = self.rotation * self.scaling * self.ad_hoc_transform;
+        self.update_transform();
+        self
+    }
 
-        // for intuitiveness, apply translation in world space instead of object space
-        self.transform[0][3] += self.translation[0][3];
-        self.transform[1][3] += self.translation[1][3];
-        self.transform[2][3] += self.translation[2][3];
+    /// Replaces the translation, the rotation and the scale of a built entity, keeping those
+    /// that are `None`. The rotation is given by angles in degrees about the x, y and z axes,
+    /// applied in that order.
+    pub fn set_pose(
+        &mut self,
+        translation: Option<Vector3>,
+        rotation: Option<Vector3>,
+        scale: Option<Vector3>,
+    ) {
+        if let Some(translation) = translation {
+            self.translation = Matrix4::translation(translation);
+        }
+        if let Some(r) = rotation {
+            self.rotation = Matrix4::rotation(Axis::Z, r.z)
+                * Matrix4::rotation(Axis::Y, r.y)
+                * Matrix4::rotation(Axis::X, r.x);
+        }
+        if let Some(scale) = scale {
+            self.scaling = Matrix4::scaling(scale);
+        }
+        self.update_transform();
+    }
+
+    fn update_transform(&mut self) {
+        self.transform = self.parent_transform
+            * compose(
+                &self.translation,
+                &self.rotation,
+                &self.scaling,
+                &self.ad_hoc_transform,
+            );
 
-        // set inverse transform
-        self.inv_transform = self.transform.invert().expect("could not invert transform");
+        // set inverse transforms, rays skip the entity while they are singular
+        self.inv_transform = self.transform.invert();
+        self.inv_parent_transform = self.parent_transform.invert();
+        self.inv_ad_hoc_transform = self.ad_hoc_transform.invert();
+    }
 
+    /// Places the entity in the space of a parent, like the group it belongs to, whose
+    /// transform applies after the own transform of the entity.
+    pub fn with_parent_transform(mut self, transform: Matrix4) -> Self {
+        self.parent_transform = transform * self.parent_transform;
+        self.update_transform();
         self
     }
+
+    /// Returns the transform from object space to world space at `time`.
+    fn transform_at(&self, time: f64) -> Matrix4 {
+        match &self.motion {
+            Some(motion) => {
+                let (translation, rotation, scale, ad_hoc_transform) = self.parts_at(motion, time);
+                let ad_hoc_transform = ad_hoc_transform.unwrap_or(self.ad_hoc_transform);
+                self.compose_parts(translation, &rotation, scale, &ad_hoc_transform)
+            }
+            None => self.transform,
+        }
+    }
+
+    /// Returns the transform at `time` along with its inverse, or `None` if the entity is
+    /// flattened at that time, like by a zero scale or a mirroring motion halfway through.
+    fn transforms_at(&self, time: f64) -> Option<(Matrix4, Matrix4)> {
+        let motion = match &self.motion {
+            Some(motion) => motion,
+            None => return self.inv_transform.map(|inv| (self.transform, inv)),
+        };
+
+        let (translation, rotation, scale, ad_hoc_transform) = self.parts_at(motion, time);
+        if scale.x * scale.y * scale.z == 0.0 {
+            return None;
+        }
+
+        // the rotation is orthonormal and the scaling diagonal, so only an ad hoc motion
+        // needs a matrix to be inverted
+        let (ad_hoc_transform, inv_ad_hoc_transform) = match ad_hoc_transform {
+            Some(ad_hoc_transform) => (ad_hoc_transform, ad_hoc_transform.invert()?),
+            None => (self.ad_hoc_transform, self.inv_ad_hoc_transform?),
+        };
+        let inv_scaling =
+            Matrix4::scaling(Vector3::new(1.0 / scale.x, 1.0 / scale.y, 1.0 / scale.z));
+        let inv_transform = inv_ad_hoc_transform
+            * inv_scaling
+            * rotation.transpose()
+            * Matrix4::translation(-translation)
+            * self.inv_parent_transform?;
+
+        let transform = self.compose_parts(translation, &rotation, scale, &ad_hoc_transform);
+        Some((transform, inv_transform))
+    }
+
+    /// Returns the translation, the rotation, the scale factors and the ad hoc transform of a
+    /// moving entity at `time`, with the motion applied. The ad hoc transform is `None` when
+    /// the motion leaves it unchanged.
+    fn parts_at(&self, motion: &Motion, time: f64) -> (Vector3, Matrix4, Vector3, Option<Matrix4>) {
+        let (translation, rotation, scale, ad_hoc_transform) = motion.at(time);
+        let t = &self.translation;
+        let s = &self.scaling;
+
+        (
+            Vector3::new(t[0][3], t[1][3], t[2][3]) + translation,
+            self.rotation * rotation,
+            Vector3::new(s[0][0] * scale.x, s[1][1] * scale.y, s[2][2] * scale.z),
+            ad_hoc_transform.map(|a| self.ad_hoc_transform * a),
+        )
+    }
+
+    fn compose_parts(
+        &self,
+        translation: Vector3,
+        rotation: &Matrix4,
+        scale: Vector3,
+        ad_hoc_transform: &Matrix4,
+    ) -> Matrix4 {
+        self.parent_transform
+            * compose(
+                &Matrix4::translation(translation),
+                rotation,
+                &Matrix4::scaling(scale),
+                ad_hoc_transform,
+            )
+    }
 }
 
 impl From<Geometry> for Entity {
@@ -51,12 +253,17 @@ impl From<Geometry> for Entity {
         Self {
             geometry,
             material: Phong::default(),
+            casts_shadows: true,
             translation: Matrix4::default(),
             rotation: Matrix4::default(),
             scaling: Matrix4::default(),
             ad_hoc_transform: Matrix4::default(),
+            motion: None,
+            parent_transform: Matrix4::default(),
             transform: Matrix4::default(),
-            inv_transform: Matrix4::default(),
+            inv_transform: Some(Matrix4::default()),
+            inv_parent_transform: Some(Matrix4::default()),
+            inv_ad_hoc_transform: Some(Matrix4::default()),
         }
     }
 }
@@ -89,37 +296,88 @@ impl From<Mesh> for Entity {
     }
 }
 
+impl From<Arc<Mesh>> for Entity {
+    fn from(mesh: Arc<Mesh>) -> Self {
+        let geometry: Geometry = mesh.into();
+        Self::from(geometry)
+    }
+}
+
 impl Intersect for Entity {
     fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        let (transform, inv_transform) = self.transforms_at(ray.time)?;
+
         // transform the ray to object space
-        let new_ray = ray.transform(self.inv_transform);
+        let new_ray = ray.transform(inv_transform);
 
         // do the intersection
         let intersection = self.geometry.intersect(&new_ray);
 
         // transform the intersection back to world space
         match intersection {
-            Some(Intersection {
-                position, normal, ..
-            }) => {
-                let new_position = position.transform(self.transform);
-                let new_normal = normal.transform(self.inv_transform.transpose());
+            Some(local) => {
+                // the texture coordinate of a moving entity depends on the time of the hit, so
+                // it is resolved while the hit is still in object space
+                let uv = match self.motion {
+                    Some(_) => Some(self.geometry.to_texture_space(&local)),
+                    None => local.uv,
+                };
+                let new_position = local.position.transform(transform);
+                let new_normal = local.normal.transform(inv_transform.transpose());
                 let new_t = ray.distance_to(new_position);
-                Some(Intersection::new(
-                    new_t,
-                    new_position,
-                    new_normal.normalize(),
-                ))
+                Some(Intersection {
+                    uv,
+                    ..Intersection::new(new_t, new_position, new_normal.normalize())
+                })
             }
             None => None,
         }
     }
 }
 
+// number of intervals the motion of an entity is split into to bound it
+const MOTION_STEPS: u32 = 16;
+
+impl Bounded for Entity {
+    /// Bounds the entity over the whole motion for moving entities.
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        let bounds = self.geometry.bounding_box()?;
+        let motion = match &self.motion {
+            Some(motion) => motion,
+            None => return Some(bounds.transform(self.transform)),
+        };
+
+        let swept = (0..=MOTION_STEPS)
+            .map(|i| bounds.transform(self.transform_at(i as f64 / MOTION_STEPS as f64)))
+            .fold(BoundingBox::EMPTY, |a, b| a.union(&b));
+
+        // between two steps rotating points bulge out of the chord joining them by the
+        // sagitta of the arc they follow, chord / 2 * tan(angle / 4), and the chord fits in
+        // the swept box
+        let step = (motion.rotation_degrees() / MOTION_STEPS as f64)
+            .to_radians()
+            .min(PI);
+        let margin = swept.extent().magnitude() / 2.0 * (step / 4.0).tan();
+        let margin = Vector3::new(margin, margin, margin);
+        Some(BoundingBox::new(swept.min - margin, swept.max + margin))
+    }
+}
+
 impl Textured for Entity {
-    fn to_texture_space(&self, p: &Point3) -> Point2 {
-        self.geometry
-            .to_texture_space(&p.transform(self.inv_transform))
+    fn to_texture_space(&self, intersection: &Intersection) -> Point2 {
+        if let (Some(_), Some(uv)) = (&self.motion, intersection.uv) {
+            return uv;
+        }
+
+        // the normal is not needed for texture lookups, so only the position is brought back.
+        // A moving entity whose transform is singular at time 0 keeps it in world space
+        let local = Intersection {
+            position: self.inv_transform.map_or(intersection.position, |inv| {
+                intersection.position.transform(inv)
+            }),
+            ..*intersection
+        };
+        self.geometry.to_texture_space(&local)
     }
 }
 
@@ -147,6 +405,8 @@ impl Transformable for Entity {
 
 #[cfg(test)]
 mod tests {
+    use crate::algebra::Point3;
+
     use super::*;
 
     #[test]
@@ -165,4 +425,114 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn zero_scale() {
+        let mut entity = Entity::from(Sphere::default()).scale(Vector3::ZERO).build();
+        let ray = Ray::new(Point3::new(0.0, 0.0, -10.0), Vector3::K);
+        assert_eq!(entity.intersect(&ray), None);
+
+        entity.set_pose(None, None, Some(Vector3::new(2.0, 2.0, 2.0)));
+        assert_eq!(entity.intersect(&ray).map(|i| i.t), Some(8.0));
+    }
+
+    #[test]
+    fn emissive_light() {
+        let emissive = Phong::default().with_emission(Color::WHITE, 2.0);
+        let sphere = Entity::from(Sphere::default())
+            .scale(Vector3::new(3.0, 3.0, 3.0))
+            .translate(Vector3::J)
+            .with_material(emissive.clone())
+            .build();
+
+        assert_eq!(
+            sphere.light(),
+            Some(Light::from(AreaLight::new(
+                AreaShape::Sphere {
+                    center: Point3::new(0.0, 1.0, 0.0),
+                    radius: 3.0
+                },
+                Color::BLACK,
+                Color::WHITE * 2.0,
+                Color::WHITE * 2.0,
+                9.0
+            )))
+        );
+
+        let cube = Entity::from(Cube::default())
+            .scale(Vector3::new(2.0, 2.0, 2.0))
+            .with_material(emissive.clone())
+            .build();
+        match cube.light() {
+            Some(Light::Mesh(light)) => assert!((light.area() - 24.0).abs() < 1e-9),
+            light => panic!("{:?}", light),
+        }
+
+        let plane = Entity::from(Plane::new(Point3::O, Vector3::J)).with_material(emissive);
+        assert_eq!(plane.light(), None);
+        assert_eq!(Entity::from(Sphere::default()).build().light(), None);
+    }
+
+    #[test]
+    fn motion() {
+        // a unit cube spinning a quarter turn around y while moving up
+        let entity = Entity::from(Cube::default())
+            .translate(Vector3::new(0.0, 0.0, 5.0))
+            .with_motion(
+                Motion::new()
+                    .rotate(Axis::Y, 90.0)
+                    .translate(Vector3::new(0.0, 4.0, 0.0)),
+            )
+            .build();
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, -10.0), Vector3::K);
+        assert!((entity.intersect(&ray).unwrap().t - 14.5).abs() < 1e-9);
+        assert!(entity.intersect(&ray.with_time(1.0)).is_none());
+
+        // halfway the cube is turned by 45 degrees and its edge faces the ray
+        let ray = Ray::new(Point3::new(0.0, 2.0, -10.0), Vector3::K).with_time(0.5);
+        let hit = entity.intersect(&ray).unwrap();
+        assert!((hit.t - (15.0 - 0.5 * 2.0_f64.sqrt())).abs() < 1e-9);
+
+        // the bounds cover the cube all along the way
+        let bounds = entity.bounding_box().unwrap();
+        for i in 0..=100 {
+            let time = i as f64 / 100.0;
+            let corners = Cube::default()
+                .bounding_box()
+                .unwrap()
+                .transform(entity.transform_at(time));
+            assert_eq!(bounds.union(&corners), bounds);
+        }
+    }
+
+    #[test]
+    fn motion_inverse() {
+        let entity = Entity::from(Sphere::default())
+            .rotate(Axis::X, 30.0)
+            .scale(Vector3::new(1.0, 2.0, 3.0))
+            .transform(Matrix4::rotation(Axis::Z, 10.0))
+            .with_motion(
+                Motion::new()
+                    .rotate(Axis::Y, 120.0)
+                    .scale(Vector3::new(2.0, 0.5, 1.0))
+                    .translate(Vector3::new(1.0, 2.0, 3.0)),
+            )
+            .with_parent_transform(Matrix4::translation(Vector3::I))
+            .build();
+
+        for time in [0.0, 0.3, 1.0] {
+            let (transform, inv_transform) = entity.transforms_at(time).unwrap();
+            assert_eq!(transform, entity.transform_at(time));
+            assert_eq!(transform * inv_transform, Matrix4::IDENTITY);
+        }
+
+        // mirroring flattens the entity halfway, where rays go through it
+        let mirrored = Entity::from(Sphere::default())
+            .with_motion(Motion::new().scale(Vector3::new(-1.0, 1.0, 1.0)))
+            .build();
+        let ray = Ray::new(Point3::new(0.0, 0.0, -10.0), Vector3::K);
+        assert!(mirrored.intersect(&ray.with_time(0.5)).is_none());
+        assert!(mirrored.intersect(&ray.with_time(0.25)).is_some());
+    }
 }
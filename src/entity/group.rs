@@ -0,0 +1,230 @@
+use crate::{
+    algebra::{Matrix4, Vector3},
+    camera::Camera,
+    geometry::Axis,
+    light::Light,
+};
+
+use super::{compose, Entity, Transformable};
+
+/// Node of a scene graph.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Entity(Box<Entity>),
+    Group(Box<Group>),
+    Light(Light),
+    Camera(Camera),
+}
+
+impl From<Entity> for Node {
+    fn from(entity: Entity) -> Self {
+        Self::Entity(Box::new(entity))
+    }
+}
+
+impl From<Group> for Node {
+    fn from(group: Group) -> Self {
+        Self::Group(Box::new(group))
+    }
+}
+
+impl From<Light> for Node {
+    fn from(light: Light) -> Self {
+        Self::Light(light)
+    }
+}
+
+impl From<Camera> for Node {
+    fn from(camera: Camera) -> Self {
+        Self::Camera(camera)
+    }
+}
+
+/// Entity, light or camera out of a flattened group, placed in the space of the parent of the
+/// group.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Leaf {
+    Entity(Box<Entity>),
+    Light(Light),
+    Camera(Camera),
+}
+
+/// Group of nodes moving together, like the parts of an articulated model.
+///
+/// The transform of the group applies to its children after their own, so a wheel placed
+/// relative to a car follows the car around. Groups are flattened into independent entities
+/// when added to a scene, with meshes shared between the copies of a group added several
+/// times.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    children: Vec<Node>,
+
+    translation: Matrix4,
+    rotation: Matrix4,
+    scaling: Matrix4,
+    ad_hoc_transform: Matrix4,
+}
+
+impl Group {
+    pub fn new() -> Self {
+        Self {
+            children: Vec::new(),
+            translation: Matrix4::default(),
+            rotation: Matrix4::default(),
+            scaling: Matrix4::default(),
+            ad_hoc_transform: Matrix4::default(),
+        }
+    }
+
+    pub fn with_child(mut self, child: impl Into<Node>) -> Self {
+        self.children.push(child.into());
+        self
+    }
+
+    pub fn children(&self) -> &[Node] {
+        &self.children
+    }
+
+    /// Returns the transform from the space of the children to the space of the parent.
+    pub fn local_transform(&self) -> Matrix4 {
+        compose(
+            &self.translation,
+            &self.rotation,
+            &self.scaling,
+            &self.ad_hoc_transform,
+        )
+    }
+
+    /// Flattens the hierarchy into its entities, lights and cameras, placed in the space of
+    /// the parent of the group.
+    pub fn flatten(self) -> Vec<Leaf> {
+        let mut leaves = Vec::new();
+        self.flatten_into(Matrix4::IDENTITY, &mut leaves);
+        leaves
+    }
+
+    fn flatten_into(self, parent: Matrix4, leaves: &mut Vec<Leaf>) {
+        let transform = parent * self.local_transform();
+        for child in self.children {
+            match child {
+                Node::Entity(entity) => {
+                    leaves.push(Leaf::Entity(Box::new(
+                        entity.with_parent_transform(transform),
+                    )));
+                }
+                Node::Group(group) => group.flatten_into(transform, leaves),
+                Node::Light(light) => leaves.push(Leaf::Light(light.transform(transform))),
+                Node::Camera(camera) => leaves.push(Leaf::Camera(camera.transform(transform))),
+            }
+        }
+    }
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transformable for Group {
+    fn translate(mut self, translation: Vector3) -> Self {
+        self.translation = self.translation * Matrix4::translation(translation);
+        self
+    }
+
+    fn rotate(mut self, axis: Axis, degrees: f64) -> Self {
+        self.rotation = self.rotation * Matrix4::rotation(axis, degrees);
+        self
+    }
+
+    fn scale(mut self, scale: Vector3) -> Self {
+        self.scaling = self.scaling * Matrix4::scaling(scale);
+        self
+    }
+
+    fn transform(mut self, transform: Matrix4) -> Self {
+        self.ad_hoc_transform = self.ad_hoc_transform * transform;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        algebra::{Point3, Ray},
+        geometry::{Face, Geometry, Intersect, Mesh, Sphere, Vertex},
+        light::PointLight,
+    };
+
+    #[test]
+    fn nested_transforms() {
+        // a wheel one unit to the right of the car, the car turned a quarter turn around y and
+        // moved forward
+        let wheel = Group::new()
+            .with_child(Entity::from(Sphere::default()))
+            .with_child(Light::from(PointLight::default()))
+            .translate(Vector3::I);
+        let car = Group::new()
+            .with_child(wheel)
+            .with_child(Camera::new(Point3::O))
+            .rotate(Axis::Y, 90.0)
+            .translate(Vector3::new(0.0, 0.0, 10.0));
+
+        let leaves = car.flatten();
+        assert_eq!(leaves.len(), 3);
+
+        let expected = Point3::new(0.0, 0.0, 9.0);
+        match &leaves[0] {
+            Leaf::Entity(entity) => {
+                let ray = Ray::new(Point3::new(0.0, 0.0, -10.0), Vector3::K);
+                let hit = entity.intersect(&ray).unwrap();
+                assert!((hit.position - (expected - Vector3::K)).magnitude() < 1e-9);
+            }
+            leaf => panic!("{:?}", leaf),
+        }
+        match &leaves[1] {
+            Leaf::Light(Light::Point(light)) => {
+                assert!((light.position - expected).magnitude() < 1e-9)
+            }
+            leaf => panic!("{:?}", leaf),
+        }
+        match &leaves[2] {
+            Leaf::Camera(camera) => {
+                assert_eq!(camera.position, Point3::new(0.0, 0.0, 10.0));
+                assert!((camera.view - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-9);
+            }
+            leaf => panic!("{:?}", leaf),
+        }
+    }
+
+    #[test]
+    fn shared_meshes() {
+        let face = Face::try_from(vec![
+            Vertex::from(Point3::new(0.0, 0.0, 0.0)),
+            Vertex::from(Point3::new(1.0, 0.0, 0.0)),
+            Vertex::from(Point3::new(0.0, 1.0, 0.0)),
+        ])
+        .unwrap();
+        let mesh = Arc::new(Mesh::from(vec![face]));
+        let wheel = Group::new().with_child(Entity::from(mesh.clone()));
+        let car = Group::new()
+            .with_child(wheel.clone().translate(Vector3::I))
+            .with_child(wheel.translate(-Vector3::I));
+
+        let leaves = car.flatten();
+        assert_eq!(Arc::strong_count(&mesh), 3);
+        for leaf in leaves {
+            match leaf {
+                Leaf::Entity(entity) => match entity.geometry() {
+                    Geometry::Mesh(m) => assert!(Arc::ptr_eq(m, &mesh)),
+                    geometry => panic!("{:?}", geometry),
+                },
+                leaf => panic!("{:?}", leaf),
+            }
+        }
+    }
+}
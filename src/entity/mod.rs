@@ -1,11 +1,16 @@
 pub use entity::Entity;
+pub use group::{Group, Leaf, Node};
+pub use motion::Motion;
 
 use crate::{
     algebra::{Matrix4, Vector3},
     geometry::Axis,
 };
 
+#[allow(clippy::module_inception)]
 mod entity;
+mod group;
+mod motion;
 
 pub trait Transformable {
     fn translate(self, translation: Vector3) -> Self;
@@ -13,3 +18,21 @@ pub trait Transformable {
     fn scale(self, scale: Vector3) -> Self;
     fn transform(self, transform: Matrix4) -> Self;
 }
+
+/// Combines the parts of a transform, applying the translation in the parent space instead of
+/// object space for intuitiveness.
+pub(crate) fn compose(
+    translation: &Matrix4,
+    rotation: &Matrix4,
+    scaling: &Matrix4,
+    ad_hoc_transform: &Matrix4,
+) -> Matrix4 {
+    // apply eveything except translation
+    let mut transform = *rotation * *scaling * *ad_hoc_transform;
+
+    transform[0][3] += translation[0][3];
+    transform[1][3] += translation[1][3];
+    transform[2][3] += translation[2][3];
+
+    transform
+}
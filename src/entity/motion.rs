@@ -0,0 +1,92 @@
+use crate::{
+    algebra::{Matrix4, Vector3},
+    geometry::Axis,
+};
+
+use super::Transformable;
+
+/// Change of the transform of an entity while the camera shutter is open, from none at time 0
+/// to all of it at time 1.
+///
+/// Like the transform of the entity, rotations and scaling apply in object space and
+/// translations in world space. Translations, rotation angles and scale factors are
+/// interpolated linearly, so a rotation of 360 degrees spins the entity a full turn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Motion {
+    translation: Vector3,
+    rotations: Vec<(Axis, f64)>,
+    scaling: Vector3,
+    ad_hoc_transform: Matrix4,
+}
+
+impl Motion {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the translation, the rotation, the scale factors and the ad hoc transform
+    /// reached at `time`, clamped to the interval between 0 and 1. The ad hoc transform is
+    /// `None` when the motion has none.
+    pub(crate) fn at(&self, time: f64) -> (Vector3, Matrix4, Vector3, Option<Matrix4>) {
+        let t = time.clamp(0.0, 1.0);
+        let rotation = self
+            .rotations
+            .iter()
+            .fold(Matrix4::IDENTITY, |r, &(axis, degrees)| {
+                r * Matrix4::rotation(axis, degrees * t)
+            });
+        let scaling = Vector3::new(1.0, 1.0, 1.0) * (1.0 - t) + self.scaling * t;
+        let ad_hoc_transform = if self.ad_hoc_transform == Matrix4::IDENTITY {
+            None
+        } else {
+            Some(Matrix4::IDENTITY * (1.0 - t) + self.ad_hoc_transform * t)
+        };
+
+        (self.translation * t, rotation, scaling, ad_hoc_transform)
+    }
+
+    /// Total angle the entity turns by, in degrees.
+    pub(crate) fn rotation_degrees(&self) -> f64 {
+        self.rotations
+            .iter()
+            .map(|(_, degrees)| degrees.abs())
+            .sum()
+    }
+}
+
+impl Default for Motion {
+    fn default() -> Self {
+        Self {
+            translation: Vector3::ZERO,
+            rotations: Vec::new(),
+            scaling: Vector3::new(1.0, 1.0, 1.0),
+            ad_hoc_transform: Matrix4::IDENTITY,
+        }
+    }
+}
+
+impl Transformable for Motion {
+    fn translate(mut self, translation: Vector3) -> Self {
+        self.translation = self.translation + translation;
+        self
+    }
+
+    fn rotate(mut self, axis: Axis, degrees: f64) -> Self {
+        self.rotations.push((axis, degrees));
+        self
+    }
+
+    fn scale(mut self, scale: Vector3) -> Self {
+        self.scaling = Vector3::new(
+            self.scaling.x * scale.x,
+            self.scaling.y * scale.y,
+            self.scaling.z * scale.z,
+        );
+        self
+    }
+
+    fn transform(mut self, transform: Matrix4) -> Self {
+        self.ad_hoc_transform = self.ad_hoc_transform * transform;
+        self
+    }
+}
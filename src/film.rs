@@ -0,0 +1,302 @@
+use std::f64::consts::PI;
+
+use image::{DynamicImage, GenericImage};
+
+use crate::{algebra::EPSILON, color::Color, texture::Image, tone_map::DisplayTransform};
+
+/// Pixel reconstruction filter used to weight the samples splatted onto the film.
+///
+/// All filters are separable, and a sample contributes to every pixel whose center lies within
+/// `radius` of it along both axes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Box {
+        radius: f64,
+    },
+    Tent {
+        radius: f64,
+    },
+    Gaussian {
+        radius: f64,
+        alpha: f64,
+    },
+    /// Mitchell-Netravali cubic, `b` and `c` trade off blurring against ringing.
+    Mitchell {
+        radius: f64,
+        b: f64,
+        c: f64,
+    },
+    /// Sinc windowed by a wider sinc, with `tau` controlling the width of the window in lobes.
+    Lanczos {
+        radius: f64,
+        tau: f64,
+    },
+}
+
+impl Filter {
+    pub fn radius(&self) -> f64 {
+        match *self {
+            Self::Box { radius }
+            | Self::Tent { radius }
+            | Self::Gaussian { radius, .. }
+            | Self::Mitchell { radius, .. }
+            | Self::Lanczos { radius, .. } => radius,
+        }
+    }
+
+    /// Weight of a sample at offset `(dx, dy)` from a pixel center.
+    pub fn evaluate(&self, dx: f64, dy: f64) -> f64 {
+        self.evaluate_1d(dx) * self.evaluate_1d(dy)
+    }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        let x = x.abs();
+        if x > self.radius() {
+            return 0.0;
+        }
+
+        match *self {
+            Self::Box { .. } => 1.0,
+            Self::Tent { radius } => radius - x,
+            Self::Gaussian { radius, alpha } => {
+                ((-alpha * x * x).exp() - (-alpha * radius * radius).exp()).max(0.0)
+            }
+            Self::Mitchell { radius, b, c } => {
+                let x = 2.0 * x / radius;
+                if x < 1.0 {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x.powi(2)
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                } else {
+                    ((-b - 6.0 * c) * x.powi(3)
+                        + (6.0 * b + 30.0 * c) * x.powi(2)
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                }
+            }
+            Self::Lanczos { tau, .. } => sinc(x) * sinc(x / tau),
+        }
+    }
+}
+
+impl Default for Filter {
+    /// A box covering exactly one pixel, so samples are simply averaged.
+    fn default() -> Self {
+        Self::Box { radius: 0.5 }
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x < 1e-5 {
+        return 1.0;
+    }
+    (PI * x).sin() / (PI * x)
+}
+
+/// Accumulation buffer between the traced samples and the final image.
+///
+/// Every sample is splatted into the pixels covered by the filter, and a pixel's color is the
+/// weighted average of its samples.
+#[derive(Debug, Clone)]
+pub struct Film {
+    pub width: u32,
+    pub height: u32,
+    pub filter: Filter,
+    pixels: Vec<FilmPixel>,
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+struct FilmPixel {
+    sum: Color,
+    weight: f64,
+    // samples inside the pixel with box weights, for when the negative lobes of the filter
+    // leave it without a positive weight
+    box_sum: Color,
+    box_weight: f64,
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32, filter: Filter) -> Self {
+        Self {
+            width,
+            height,
+            filter,
+            pixels: vec![FilmPixel::default(); (width * height) as usize],
+        }
+    }
+
+    /// Adds a sample taken at `(x, y)`, where integer coordinates are the centers of the pixels.
+    pub fn add_sample(&mut self, x: f64, y: f64, color: Color) {
+        let radius = self.filter.radius();
+        let x0 = ((x - radius).ceil() as i64).max(0);
+        let x1 = ((x + radius).floor() as i64).min(self.width as i64 - 1);
+        let y0 = ((y - radius).ceil() as i64).max(0);
+        let y1 = ((y + radius).floor() as i64).min(self.height as i64 - 1);
+
+        let (bx, by) = (x.round() as i64, y.round() as i64);
+        if (0..self.width as i64).contains(&bx) && (0..self.height as i64).contains(&by) {
+            let pixel = &mut self.pixels[(by * self.width as i64 + bx) as usize];
+            pixel.box_sum = pixel.box_sum + color;
+            pixel.box_weight += 1.0;
+        }
+
+        for py in y0..=y1 {
+            for px in x0..=x1 {
+                let weight = self.filter.evaluate(px as f64 - x, py as f64 - y);
+                if weight == 0.0 {
+                    continue;
+                }
+
+                let pixel = &mut self.pixels[(py * self.width as i64 + px) as usize];
+                pixel.sum = pixel.sum + color * weight;
+                pixel.weight += weight;
+            }
+        }
+    }
+
+    /// Adds the samples accumulated on another film of the same size.
+    pub fn merge(&mut self, other: &Film) {
+        for (pixel, other) in self.pixels.iter_mut().zip(&other.pixels) {
+            pixel.sum = pixel.sum + other.sum;
+            pixel.weight += other.weight;
+            pixel.box_sum = pixel.box_sum + other.box_sum;
+            pixel.box_weight += other.box_weight;
+        }
+    }
+
+    /// Returns the reconstructed color of a pixel, or black if no sample reached it.
+    ///
+    /// Filters with negative lobes can ring below zero next to bright edges, which is clamped
+    /// away, and leave sparsely sampled pixels without a positive total weight, which fall
+    /// back to the average of the samples inside them.
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        let pixel = self.pixels[(y * self.width + x) as usize];
+        let color = if pixel.weight > EPSILON {
+            pixel.sum * (1.0 / pixel.weight)
+        } else if pixel.box_weight > 0.0 {
+            pixel.box_sum * (1.0 / pixel.box_weight)
+        } else {
+            return Color::BLACK;
+        };
+        Color::new(color.r.max(0.0), color.g.max(0.0), color.b.max(0.0))
+    }
+
+    /// Returns the reconstructed linear radiance of every pixel, without any display transform,
+    /// for saving as a floating point image.
+    pub fn to_linear_image(&self) -> Image {
+        let pixels = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| self.pixel(x, y))
+            .collect();
+        Image::new(self.width, self.height, pixels)
+    }
+
+    pub fn to_image(&self, display: &DisplayTransform) -> DynamicImage {
+        let mut img = DynamicImage::new_rgb8(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                img.put_pixel(x, y, display.apply(self.pixel(x, y)).into());
+            }
+        }
+        img
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILTERS: [Filter; 5] = [
+        Filter::Box { radius: 0.5 },
+        Filter::Tent { radius: 1.0 },
+        Filter::Gaussian {
+            radius: 1.5,
+            alpha: 2.0,
+        },
+        Filter::Mitchell {
+            radius: 2.0,
+            b: 1.0 / 3.0,
+            c: 1.0 / 3.0,
+        },
+        Filter::Lanczos {
+            radius: 3.0,
+            tau: 3.0,
+        },
+    ];
+
+    #[test]
+    fn filters_peak_at_center() {
+        for filter in &FILTERS {
+            let center = filter.evaluate(0.0, 0.0);
+            assert!(center > 0.0, "{:?}", filter);
+            assert!(filter.evaluate(0.3, 0.1) <= center, "{:?}", filter);
+            assert_eq!(filter.evaluate(filter.radius() + 0.1, 0.0), 0.0);
+        }
+    }
+
+    #[test]
+    fn box_filter_averages() {
+        let mut film = Film::new(3, 2, Filter::default());
+        film.add_sample(1.2, 0.9, Color::WHITE);
+        film.add_sample(0.8, 1.1, Color::BLACK);
+        film.add_sample(2.0, 0.0, Color::RED);
+
+        assert_eq!(film.pixel(1, 1), Color::WHITE * 0.5);
+        assert_eq!(film.pixel(2, 0), Color::RED);
+        assert_eq!(film.pixel(0, 0), Color::BLACK);
+    }
+
+    #[test]
+    fn wide_filter_splats_into_neighbours() {
+        let mut film = Film::new(4, 4, Filter::Tent { radius: 1.5 });
+        film.add_sample(1.0, 1.0, Color::WHITE);
+
+        let mut other = Film::new(4, 4, Filter::Tent { radius: 1.5 });
+        other.add_sample(3.0, 3.0, Color::BLUE);
+        film.merge(&other);
+
+        assert_eq!(film.pixel(0, 2), Color::WHITE);
+        assert_eq!(film.pixel(3, 3), Color::BLUE);
+        assert_eq!(film.pixel(3, 0), Color::BLACK);
+        // pixel (2, 2) is reached by both samples with the same weight
+        assert_eq!(film.pixel(2, 2), (Color::WHITE + Color::BLUE) * 0.5);
+    }
+
+    #[test]
+    fn sparse_samples_stay_positive() {
+        // one or two samples per pixel with the negative lobes of Lanczos, a bright half next
+        // to a dark one
+        let mut film = Film::new(
+            8,
+            8,
+            Filter::Lanczos {
+                radius: 3.0,
+                tau: 3.0,
+            },
+        );
+        for y in 0..8 {
+            for x in 0..8 {
+                let color = if x < 4 {
+                    Color::WHITE * 100.0
+                } else {
+                    Color::BLACK
+                };
+                for i in 0..(x + y) % 2 + 1 {
+                    let offset = 0.37 * (i + x * 3 + y * 5) as f64 % 1.0 - 0.5;
+                    film.add_sample(x as f64 + offset, y as f64 - offset * 0.8, color);
+                }
+            }
+        }
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let c = film.pixel(x, y);
+                for v in [c.r, c.g, c.b] {
+                    assert!(v.is_finite() && v >= 0.0, "({}, {}) {:?}", x, y, c);
+                }
+            }
+        }
+    }
+}
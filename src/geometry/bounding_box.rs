@@ -0,0 +1,205 @@
+use crate::{
+    algebra::{Matrix4, Point3, Ray, Vector3},
+    entity::Transformable,
+};
+
+use super::Axis;
+
+/// Axis aligned bounding box, used by the acceleration structures.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl BoundingBox {
+    /// A box containing nothing, the identity for `union`.
+    pub const EMPTY: Self = Self {
+        min: Point3 {
+            x: f64::INFINITY,
+            y: f64::INFINITY,
+            z: f64::INFINITY,
+        },
+        max: Point3 {
+            x: -f64::INFINITY,
+            y: -f64::INFINITY,
+            z: -f64::INFINITY,
+        },
+    };
+
+    pub fn new(min: Point3, max: Point3) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Point3>) -> Self {
+        points
+            .into_iter()
+            .fold(Self::EMPTY, |bounds, p| bounds.include(p))
+    }
+
+    pub fn include(&self, p: &Point3) -> Self {
+        Self::new(
+            Point3::new(
+                self.min.x.min(p.x),
+                self.min.y.min(p.y),
+                self.min.z.min(p.z),
+            ),
+            Point3::new(
+                self.max.x.max(p.x),
+                self.max.y.max(p.y),
+                self.max.z.max(p.z),
+            ),
+        )
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        self.include(&other.min).include(&other.max)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn extent(&self) -> Vector3 {
+        self.max - self.min
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        self.min + self.extent() * 0.5
+    }
+
+    pub fn longest_axis(&self) -> Axis {
+        let extent = self.extent();
+        if extent.x >= extent.y && extent.x >= extent.z {
+            Axis::X
+        } else if extent.y >= extent.z {
+            Axis::Y
+        } else {
+            Axis::Z
+        }
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+
+        let e = self.extent();
+        2.0 * (e.x * e.y + e.y * e.z + e.z * e.x)
+    }
+
+    pub fn corners(&self) -> [Point3; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Point3::new(a.x, a.y, a.z),
+            Point3::new(b.x, a.y, a.z),
+            Point3::new(a.x, b.y, a.z),
+            Point3::new(b.x, b.y, a.z),
+            Point3::new(a.x, a.y, b.z),
+            Point3::new(b.x, a.y, b.z),
+            Point3::new(a.x, b.y, b.z),
+            Point3::new(b.x, b.y, b.z),
+        ]
+    }
+
+    /// Slab test against the ray, given the precomputed reciprocal of its direction. Returns
+    /// the distance at which the ray enters the box, if that happens before `t_max`.
+    pub fn hit(&self, ray: &Ray, inv_dir: &Vector3, t_max: f64) -> Option<f64> {
+        let mut t0 = 0.0_f64;
+        let mut t1 = t_max;
+
+        for axis in [Axis::X, Axis::Y, Axis::Z].iter() {
+            let near = (self.min[*axis] - ray.origin[*axis]) * inv_dir[*axis];
+            let far = (self.max[*axis] - ray.origin[*axis]) * inv_dir[*axis];
+            let (near, far) = if near > far { (far, near) } else { (near, far) };
+
+            // written so that a NaN from 0 * inf leaves the interval untouched
+            t0 = if near > t0 { near } else { t0 };
+            t1 = if far < t1 { far } else { t1 };
+
+            if t0 > t1 {
+                return None;
+            }
+        }
+
+        Some(t0)
+    }
+}
+
+impl Default for BoundingBox {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+impl Transformable for BoundingBox {
+    fn translate(self, translation: Vector3) -> Self {
+        self.transform(Matrix4::translation(translation))
+    }
+
+    fn rotate(self, axis: Axis, degrees: f64) -> Self {
+        self.transform(Matrix4::rotation(axis, degrees))
+    }
+
+    fn scale(self, scale: Vector3) -> Self {
+        self.transform(Matrix4::scaling(scale))
+    }
+
+    fn transform(self, transform: Matrix4) -> Self {
+        if self.is_empty() {
+            return self;
+        }
+
+        let corners = self.corners();
+        let transformed = corners
+            .iter()
+            .map(|p| transform * *p)
+            .collect::<Vec<Point3>>();
+        Self::from_points(transformed.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn union() {
+        let a = BoundingBox::new(Point3::O, Point3::new(1.0, 1.0, 1.0));
+        let b = BoundingBox::new(Point3::new(-1.0, 0.5, 0.0), Point3::new(0.0, 2.0, 0.5));
+
+        assert_eq!(
+            a.union(&b),
+            BoundingBox::new(Point3::new(-1.0, 0.0, 0.0), Point3::new(1.0, 2.0, 1.0))
+        );
+        assert_eq!(BoundingBox::EMPTY.union(&a), a);
+    }
+
+    #[test]
+    fn transform() {
+        let actual = BoundingBox::new(Point3::O, Point3::new(1.0, 2.0, 3.0))
+            .rotate(Axis::Y, 90.0)
+            .translate(Vector3::I);
+        let expected = BoundingBox::new(Point3::new(1.0, 0.0, -1.0), Point3::new(4.0, 2.0, 0.0));
+
+        assert!((actual.min - expected.min).magnitude() < 1e-9);
+        assert!((actual.max - expected.max).magnitude() < 1e-9);
+    }
+
+    #[test]
+    fn hit() {
+        let bounds = BoundingBox::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::K);
+        assert_eq!(bounds.hit(&ray, &(1.0 / ray.dir), f64::INFINITY), Some(4.0));
+        assert_eq!(bounds.hit(&ray, &(1.0 / ray.dir), 3.0), None);
+
+        // origin inside the box
+        let ray = Ray::new(Point3::O, Vector3::J);
+        assert_eq!(bounds.hit(&ray, &(1.0 / ray.dir), f64::INFINITY), Some(0.0));
+
+        // miss
+        let ray = Ray::new(Point3::new(0.0, 2.0, -5.0), Vector3::K);
+        assert_eq!(bounds.hit(&ray, &(1.0 / ray.dir), f64::INFINITY), None);
+    }
+}
@@ -0,0 +1,310 @@
+use std::cmp::Ordering;
+
+use crate::algebra::{Point3, Ray};
+
+use super::{Axis, BoundingBox, Intersection};
+
+const MAX_LEAF_SIZE: usize = 4;
+const NUM_BUCKETS: usize = 12;
+// cost of visiting an interior node, relative to the cost of intersecting a primitive
+const TRAVERSAL_COST: f64 = 0.125;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Node {
+    bounds: BoundingBox,
+    // for leaves this is the start of its primitives in `indices`, for interior nodes it is
+    // the index of the second child, the first child always directly follows its parent
+    offset: usize,
+    // number of primitives in a leaf, 0 for interior nodes
+    count: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Primitive {
+    index: usize,
+    bounds: BoundingBox,
+    centroid: Point3,
+}
+
+/// Bounding volume hierarchy over a set of primitives identified by an index. The hierarchy
+/// only knows about bounding boxes, the actual intersection test is supplied by the caller
+/// during traversal.
+///
+/// Nodes are split using the surface area heuristic, evaluated over a fixed number of buckets
+/// along the axis where the primitive centroids are most spread out.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    indices: Vec<usize>,
+}
+
+impl Bvh {
+    pub fn new(primitives: impl IntoIterator<Item = (usize, BoundingBox)>) -> Self {
+        let mut primitives = primitives
+            .into_iter()
+            .filter(|(_, bounds)| !bounds.is_empty())
+            .map(|(index, bounds)| Primitive {
+                index,
+                bounds,
+                centroid: bounds.centroid(),
+            })
+            .collect::<Vec<Primitive>>();
+
+        let mut bvh = Self::default();
+        if !primitives.is_empty() {
+            bvh.nodes.reserve(2 * primitives.len());
+            bvh.indices.reserve(primitives.len());
+            bvh.build(&mut primitives);
+        }
+
+        bvh
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox {
+        self.nodes
+            .first()
+            .map_or(BoundingBox::EMPTY, |node| node.bounds)
+    }
+
+    /// Returns the closest hit closer than `t_max`, along with the index of the primitive that
+    /// was hit. `intersect` is called with the index of every primitive whose bounds are hit.
+    pub fn intersect<F>(
+        &self,
+        ray: &Ray,
+        t_max: f64,
+        mut intersect: F,
+    ) -> Option<(usize, Intersection)>
+    where
+        F: FnMut(usize) -> Option<Intersection>,
+    {
+        let inv_dir = 1.0 / ray.dir;
+        let mut t_max = t_max;
+        let mut closest = None;
+
+        let mut stack = Vec::with_capacity(32);
+        if let Some(t) = self
+            .nodes
+            .first()
+            .and_then(|n| n.bounds.hit(ray, &inv_dir, t_max))
+        {
+            stack.push((0, t));
+        }
+
+        while let Some((i, t_enter)) = stack.pop() {
+            if t_enter > t_max {
+                continue;
+            }
+
+            let node = &self.nodes[i];
+            if node.count > 0 {
+                for &index in &self.indices[node.offset..node.offset + node.count] {
+                    if let Some(hit) = intersect(index) {
+                        if hit.t < t_max {
+                            t_max = hit.t;
+                            closest = Some((index, hit));
+                        }
+                    }
+                }
+                continue;
+            }
+
+            let first = (i + 1, self.nodes[i + 1].bounds.hit(ray, &inv_dir, t_max));
+            let second = (
+                node.offset,
+                self.nodes[node.offset].bounds.hit(ray, &inv_dir, t_max),
+            );
+
+            // push the farther child first so that the nearer one is visited first
+            let (near, far) = match (first.1, second.1) {
+                (Some(t1), Some(t2)) if t2 < t1 => (second, first),
+                _ => (first, second),
+            };
+            for (child, t) in [far, near].iter() {
+                if let Some(t) = t {
+                    stack.push((*child, *t));
+                }
+            }
+        }
+
+        closest
+    }
+
+    fn build(&mut self, primitives: &mut [Primitive]) -> usize {
+        let bounds = primitives
+            .iter()
+            .fold(BoundingBox::EMPTY, |acc, p| acc.union(&p.bounds));
+
+        let node_index = self.nodes.len();
+        self.nodes.push(Node {
+            bounds,
+            offset: self.indices.len(),
+            count: primitives.len(),
+        });
+
+        let mid = match Self::split(primitives, &bounds) {
+            Some(mid) => mid,
+            None => {
+                self.indices.extend(primitives.iter().map(|p| p.index));
+                return node_index;
+            }
+        };
+
+        let (left, right) = primitives.split_at_mut(mid);
+        self.build(left);
+        let second = self.build(right);
+
+        self.nodes[node_index].offset = second;
+        self.nodes[node_index].count = 0;
+
+        node_index
+    }
+
+    /// Partitions the primitives and returns the index of the split, or `None` if the primitives
+    /// are better off in a single leaf.
+    fn split(primitives: &mut [Primitive], bounds: &BoundingBox) -> Option<usize> {
+        let n = primitives.len();
+        if n == 1 {
+            return None;
+        }
+
+        let centroid_bounds = BoundingBox::from_points(primitives.iter().map(|p| &p.centroid));
+        let axis = centroid_bounds.longest_axis();
+        let min = centroid_bounds.min[axis];
+        let extent = centroid_bounds.extent()[axis];
+
+        if extent <= 0.0 || !extent.is_finite() {
+            // every centroid is at the same place, the buckets can't tell them apart
+            return if n <= MAX_LEAF_SIZE {
+                None
+            } else {
+                Some(Self::split_median(primitives, axis))
+            };
+        }
+
+        let bucket_of = |p: &Primitive| {
+            let b = ((p.centroid[axis] - min) / extent * NUM_BUCKETS as f64) as usize;
+            b.min(NUM_BUCKETS - 1)
+        };
+
+        let mut counts = [0; NUM_BUCKETS];
+        let mut bucket_bounds = [BoundingBox::EMPTY; NUM_BUCKETS];
+        for p in primitives.iter() {
+            let b = bucket_of(p);
+            counts[b] += 1;
+            bucket_bounds[b] = bucket_bounds[b].union(&p.bounds);
+        }
+
+        // cost of splitting after each bucket, accumulated from both ends
+        let mut costs = [0.0; NUM_BUCKETS - 1];
+        let (mut count, mut acc) = (0, BoundingBox::EMPTY);
+        for i in 0..NUM_BUCKETS - 1 {
+            count += counts[i];
+            acc = acc.union(&bucket_bounds[i]);
+            costs[i] = count as f64 * acc.surface_area();
+        }
+        let (mut count, mut acc) = (0, BoundingBox::EMPTY);
+        for i in (1..NUM_BUCKETS).rev() {
+            count += counts[i];
+            acc = acc.union(&bucket_bounds[i]);
+            costs[i - 1] += count as f64 * acc.surface_area();
+        }
+
+        let (best, best_cost) = costs
+            .iter()
+            .map(|c| TRAVERSAL_COST + c / bounds.surface_area())
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+            .unwrap();
+
+        if n <= MAX_LEAF_SIZE && n as f64 <= best_cost {
+            return None;
+        }
+
+        // partition in place, everything up to and including the best bucket goes left
+        let mut mid = 0;
+        for i in 0..n {
+            if bucket_of(&primitives[i]) <= best {
+                primitives.swap(i, mid);
+                mid += 1;
+            }
+        }
+
+        if mid == 0 || mid == n {
+            return Some(Self::split_median(primitives, axis));
+        }
+
+        Some(mid)
+    }
+
+    fn split_median(primitives: &mut [Primitive], axis: Axis) -> usize {
+        let mid = primitives.len() / 2;
+        primitives.select_nth_unstable_by(mid, |a, b| {
+            a.centroid[axis]
+                .partial_cmp(&b.centroid[axis])
+                .unwrap_or(Ordering::Equal)
+        });
+
+        mid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::algebra::Vector3;
+    use crate::geometry::{Bounded, Intersect, Sphere};
+
+    use super::*;
+
+    fn spheres() -> Vec<Sphere> {
+        (0..50)
+            .map(|i| {
+                let i = i as f64;
+                Sphere::new(
+                    Point3::new((i * 7.0) % 13.0, (i * 3.0) % 11.0, i),
+                    0.5 + (i % 3.0) * 0.25,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let spheres = spheres();
+        let bvh = Bvh::new(
+            spheres
+                .iter()
+                .enumerate()
+                .map(|(i, s)| (i, s.bounding_box().unwrap())),
+        );
+
+        for i in 0..100 {
+            let i = i as f64;
+            let ray = Ray::new(
+                Point3::new(-10.0, 5.0, -10.0),
+                Vector3::new(1.0 + i * 0.1, (i * 0.37).sin(), 1.0 + (i * 0.11).cos()).normalize(),
+            );
+
+            let expected = spheres
+                .iter()
+                .enumerate()
+                .filter_map(|(i, s)| s.intersect(&ray).map(|hit| (i, hit)))
+                .min_by(|(_, i1), (_, i2)| i1.partial_cmp(i2).unwrap());
+            let actual = bvh.intersect(&ray, f64::INFINITY, |i| spheres[i].intersect(&ray));
+
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let bvh = Bvh::new(vec![]);
+        let ray = Ray::new(Point3::O, Vector3::K);
+
+        assert!(bvh.is_empty());
+        assert_eq!(bvh.intersect(&ray, f64::INFINITY, |_| None), None);
+    }
+}
@@ -1,6 +1,6 @@
 use crate::algebra::{Point2, Point3, Ray, Vector3};
 
-use super::{Intersect, Intersection, Textured};
+use super::{Bounded, BoundingBox, Intersect, Intersection, Textured};
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Cube {
@@ -22,6 +22,36 @@ impl Cube {
         self.flipped_normals = true;
         self
     }
+
+    /// Returns the six faces of the cube split into two triangles each.
+    pub fn triangles(&self) -> Vec<[Point3; 3]> {
+        let (min, max) = (self.min_bounds, self.max_bounds);
+        let corner = |i: usize| {
+            Point3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        };
+
+        // corner indices of every face, going around it
+        [
+            [0, 2, 6, 4],
+            [1, 3, 7, 5],
+            [0, 1, 5, 4],
+            [2, 3, 7, 6],
+            [0, 1, 3, 2],
+            [4, 5, 7, 6],
+        ]
+        .iter()
+        .flat_map(|&[a, b, c, d]| {
+            vec![
+                [corner(a), corner(b), corner(c)],
+                [corner(a), corner(c), corner(d)],
+            ]
+        })
+        .collect()
+    }
 }
 
 impl Default for Cube {
@@ -111,8 +141,15 @@ impl Intersect for Cube {
     }
 }
 
+impl Bounded for Cube {
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        Some(BoundingBox::new(self.min_bounds, self.max_bounds))
+    }
+}
+
 impl Textured for Cube {
-    fn to_texture_space(&self, p: &Point3) -> Point2 {
+    fn to_texture_space(&self, intersection: &Intersection) -> Point2 {
+        let p = &intersection.position;
         let abs_p = Point3::new(p.x.abs(), p.y.abs(), p.z.abs());
 
         let uc: f64;
@@ -1,18 +1,20 @@
 use std::cmp::Ordering;
+use std::sync::Arc;
 
 use crate::algebra::{Point2, Ray};
 use crate::algebra::{Point3, Vector3};
 
 use super::Plane;
 use super::Sphere;
-use super::{Cube, Mesh};
+use super::{BoundingBox, Cube, Mesh};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Geometry {
     Sphere(Sphere),
     Plane(Plane),
     Cube(Cube),
-    Mesh(Mesh),
+    /// Meshes are shared between the entities instancing them.
+    Mesh(Arc<Mesh>),
 }
 
 pub trait Intersect {
@@ -20,7 +22,12 @@ pub trait Intersect {
 }
 
 pub trait Textured {
-    fn to_texture_space(&self, p: &Point3) -> Point2;
+    fn to_texture_space(&self, intersection: &Intersection) -> Point2;
+}
+
+pub trait Bounded {
+    /// Returns the axis aligned bounds of the object, or `None` if it is unbounded.
+    fn bounding_box(&self) -> Option<BoundingBox>;
 }
 
 impl Intersect for Geometry {
@@ -35,12 +42,23 @@ impl Intersect for Geometry {
 }
 
 impl Textured for Geometry {
-    fn to_texture_space(&self, p: &Point3) -> Point2 {
+    fn to_texture_space(&self, intersection: &Intersection) -> Point2 {
+        match self {
+            Self::Sphere(x) => x.to_texture_space(intersection),
+            Self::Plane(x) => x.to_texture_space(intersection),
+            Self::Cube(x) => x.to_texture_space(intersection),
+            Self::Mesh(x) => x.to_texture_space(intersection),
+        }
+    }
+}
+
+impl Bounded for Geometry {
+    fn bounding_box(&self) -> Option<BoundingBox> {
         match self {
-            Self::Sphere(x) => x.to_texture_space(p),
-            Self::Plane(x) => x.to_texture_space(p),
-            Self::Cube(x) => x.to_texture_space(p),
-            Self::Mesh(x) => x.to_texture_space(p),
+            Self::Sphere(x) => x.bounding_box(),
+            Self::Plane(x) => x.bounding_box(),
+            Self::Cube(x) => x.bounding_box(),
+            Self::Mesh(x) => x.bounding_box(),
         }
     }
 }
@@ -65,6 +83,12 @@ impl From<Cube> for Geometry {
 
 impl From<Mesh> for Geometry {
     fn from(m: Mesh) -> Self {
+        Geometry::Mesh(Arc::new(m))
+    }
+}
+
+impl From<Arc<Mesh>> for Geometry {
+    fn from(m: Arc<Mesh>) -> Self {
         Geometry::Mesh(m)
     }
 }
@@ -74,6 +98,9 @@ pub struct Intersection {
     pub t: f64,
     pub position: Point3,
     pub normal: Vector3,
+    /// Texture coordinate of the hit, for geometry that carries its own, like meshes with
+    /// per vertex coordinates.
+    pub uv: Option<Point2>,
 }
 
 impl Intersection {
@@ -82,8 +109,14 @@ impl Intersection {
             t,
             position,
             normal,
+            uv: None,
         }
     }
+
+    pub fn with_uv(mut self, uv: Point2) -> Self {
+        self.uv = Some(uv);
+        self
+    }
 }
 
 impl PartialOrd for Intersection {
@@ -1,4 +1,7 @@
+pub use bounding_box::BoundingBox;
+pub use bvh::Bvh;
 pub use cube::Cube;
+pub use geometry::Bounded;
 pub use geometry::Geometry;
 pub use geometry::Intersect;
 pub use geometry::Intersection;
@@ -9,7 +12,10 @@ pub use polygon::Mesh;
 pub use polygon::Vertex;
 pub use sphere::Sphere;
 
+mod bounding_box;
+mod bvh;
 mod cube;
+#[allow(clippy::module_inception)]
 mod geometry;
 mod plane;
 mod polygon;
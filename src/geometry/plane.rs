@@ -4,6 +4,8 @@ use crate::algebra::Ray;
 use crate::algebra::Vector3;
 use crate::algebra::EPSILON;
 
+use super::Bounded;
+use super::BoundingBox;
 use super::Intersect;
 use super::Intersection;
 use super::Textured;
@@ -49,8 +51,15 @@ impl Intersect for Plane {
     }
 }
 
+impl Bounded for Plane {
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        None
+    }
+}
+
 impl Textured for Plane {
-    fn to_texture_space(&self, p: &Point3) -> Point2 {
+    fn to_texture_space(&self, intersection: &Intersection) -> Point2 {
+        let p = &intersection.position;
         let mut candidate = self.normal.cross(&Vector3::K);
         if candidate.magnitude() < EPSILON {
             candidate = self.normal.cross(&(-Vector3::J));
@@ -8,12 +8,13 @@ use ply_rs::{
 
 use crate::algebra::{Point2, Point3, Ray, Vector3, EPSILON};
 
-use super::{Cube, Intersect, Intersection, Plane, Textured};
+use super::{Bounded, BoundingBox, Bvh, Intersect, Intersection, Plane, Textured};
 
 #[derive(Default, Debug, Clone, Copy, PartialEq)]
 pub struct Vertex {
     pub point: Point3,
     pub normal: Vector3,
+    pub uv: Option<Point2>,
 }
 
 impl Vertex {
@@ -21,6 +22,11 @@ impl Vertex {
         self.normal = normal;
         self
     }
+
+    pub fn with_uv(mut self, uv: Point2) -> Self {
+        self.uv = Some(uv);
+        self
+    }
 }
 
 impl From<Point3> for Vertex {
@@ -28,6 +34,7 @@ impl From<Point3> for Vertex {
         Self {
             point,
             normal: Vector3::default(),
+            uv: None,
         }
     }
 }
@@ -57,6 +64,90 @@ impl TryFrom<Vec<Vertex>> for Face {
     }
 }
 
+impl Face {
+    /// Locates `p`, a point on the face, within the fan of triangles `(0, i, i + 1)` that make
+    /// up the polygon. Returns `i` along with the barycentric coordinates of `p` in that triangle.
+    pub fn barycentric(&self, p: &Point3) -> Option<(usize, [f64; 3])> {
+        let a = self.vertices[0].point;
+
+        (1..self.vertices.len() - 1).find_map(|i| {
+            let b = self.vertices[i].point;
+            let c = self.vertices[i + 1].point;
+
+            let n = (b - a).cross(&(c - a));
+            let denom = n.norm();
+            if denom < EPSILON * EPSILON {
+                // degenerate triangle
+                return None;
+            }
+
+            let u = (c - b).cross(&(*p - b)).dot(&n) / denom;
+            let v = (a - c).cross(&(*p - c)).dot(&n) / denom;
+            let w = 1.0 - u - v;
+
+            if u > -EPSILON && v > -EPSILON && w > -EPSILON {
+                Some((i, [u, v, w]))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the shading normal at `p`, a point on the face. The vertex normals are
+    /// interpolated if every vertex has one, otherwise this is just the face normal.
+    pub fn normal_at(&self, p: &Point3) -> Vector3 {
+        self.interpolate_normal(self.barycentric(p))
+    }
+
+    /// Returns the texture coordinate at `p`, a point on the face, if every vertex has one.
+    pub fn uv_at(&self, p: &Point3) -> Option<Point2> {
+        self.interpolate_uv(self.barycentric(p))
+    }
+
+    fn interpolate_normal(&self, barycentric: Option<(usize, [f64; 3])>) -> Vector3 {
+        if self.vertices.iter().any(|v| v.normal == Vector3::ZERO) {
+            return self.normal;
+        }
+
+        match barycentric {
+            Some((i, [u, v, w])) => {
+                let n = u * self.vertices[0].normal
+                    + v * self.vertices[i].normal
+                    + w * self.vertices[i + 1].normal;
+
+                // keep the shading normal on the same side as the face
+                if n.dot(&self.normal) < 0.0 {
+                    -n.normalize()
+                } else {
+                    n.normalize()
+                }
+            }
+            None => self.normal,
+        }
+    }
+
+    fn interpolate_uv(&self, barycentric: Option<(usize, [f64; 3])>) -> Option<Point2> {
+        let (i, [u, v, w]) = barycentric?;
+
+        Some(u * self.vertices[0].uv? + v * self.vertices[i].uv? + w * self.vertices[i + 1].uv?)
+    }
+
+    /// Normal of the polygon weighted by its area, computed with Newell's method. Oriented the
+    /// same way as the face normal, which follows the clockwise winding of the vertices.
+    pub(crate) fn area_weighted_normal(points: &[Point3]) -> Vector3 {
+        let n = points.len();
+        (0..n).fold(Vector3::ZERO, |acc, i| {
+            let a = points[i];
+            let b = points[(i + 1) % n];
+            acc + Vector3::new(
+                (a.y - b.y) * (a.z + b.z),
+                (a.z - b.z) * (a.x + b.x),
+                (a.x - b.x) * (a.y + b.y),
+            )
+        }) * -0.5
+    }
+}
+
 impl Intersect for Face {
     fn intersect(&self, ray: &Ray) -> Option<Intersection> {
         let plane = Plane::new(self.vertices[0].point, self.normal);
@@ -73,7 +164,16 @@ impl Intersect for Face {
                 });
 
                 if inside {
-                    intersection
+                    let barycentric = self.barycentric(&c);
+                    let normal = self.interpolate_normal(barycentric);
+
+                    intersection.map(|x| {
+                        let hit = Intersection::new(x.t, c, normal);
+                        match self.interpolate_uv(barycentric) {
+                            Some(uv) => hit.with_uv(uv),
+                            None => hit,
+                        }
+                    })
                 } else {
                     None
                 }
@@ -83,15 +183,23 @@ impl Intersect for Face {
     }
 }
 
+impl Bounded for Face {
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        Some(BoundingBox::from_points(
+            self.vertices.iter().map(|v| &v.point),
+        ))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Mesh {
     pub faces: Vec<Face>,
-    bounding_box: Cube,
+    bvh: Bvh,
 }
 
 impl Mesh {
     pub fn from_ply_file(path: &str) -> Result<Self, String> {
-        let mut file = File::open(path).or_else(|_| Err("Cannot read file".to_string()))?;
+        let mut file = File::open(path).map_err(|_| "Cannot read file".to_string())?;
 
         // create a parser
         let parser = Parser::<DefaultElement>::new();
@@ -99,107 +207,306 @@ impl Mesh {
         // use the parser: read the entire file
         let ply = parser
             .read_ply(&mut file)
-            .or_else(|_| Err("Cannot parse into ply".to_string()))?;
+            .map_err(|_| "Cannot parse into ply".to_string())?;
 
-        let faces = ply.payload["face"]
+        let vertices = ply
+            .payload
+            .get("vertex")
+            .ok_or_else(|| "No vertex element".to_string())?
             .iter()
-            .map(|f| {
-                let vertex_indices = match &f["vertex_indices"] {
-                    Property::ListInt(indices) => indices.clone(),
-                    _ => vec![],
+            .map(|vp| {
+                let point = Point3::new(
+                    ply_scalar(vp, "x").unwrap_or(0.0),
+                    ply_scalar(vp, "y").unwrap_or(0.0),
+                    ply_scalar(vp, "z").unwrap_or(0.0),
+                );
+
+                let normal = match (
+                    ply_scalar(vp, "nx"),
+                    ply_scalar(vp, "ny"),
+                    ply_scalar(vp, "nz"),
+                ) {
+                    (Some(x), Some(y), Some(z)) => Vector3::new(x, y, z),
+                    _ => Vector3::ZERO,
                 };
 
-                let vertices = vertex_indices
+                let vertex = Vertex::from(point).with_normal(normal);
+                let uv = [("u", "v"), ("s", "t"), ("texture_u", "texture_v")]
                     .iter()
-                    .rev()
-                    .map(|i| {
-                        let vp = &ply.payload["vertex"][*i as usize];
-
-                        let x: f64 = match vp["x"] {
-                            Property::Float(val) => val.into(),
-                            _ => 0.0,
-                        };
-                        let y: f64 = match vp["y"] {
-                            Property::Float(val) => val.into(),
-                            _ => 0.0,
-                        };
-                        let z: f64 = match vp["z"] {
-                            Property::Float(val) => val.into(),
-                            _ => 0.0,
-                        };
-
-                        Vertex::from(Point3::new(x, y, z))
-                    })
-                    .collect::<Vec<Vertex>>();
+                    .find_map(|(u, v)| Some(Point2::new(ply_scalar(vp, u)?, ply_scalar(vp, v)?)));
 
-                Face::try_from(vertices).expect("Invalid polygon face")
+                match uv {
+                    Some(uv) => vertex.with_uv(uv),
+                    None => vertex,
+                }
             })
-            .collect::<Vec<Face>>();
+            .collect::<Vec<Vertex>>();
 
-        Ok(Self::from(faces))
-    }
+        let faces = ply
+            .payload
+            .get("face")
+            .ok_or_else(|| "No face element".to_string())?
+            .iter()
+            .map(|f| {
+                // reversed so that the face normal agrees with the winding of the file
+                ply_indices(f).map(|indices| indices.into_iter().rev().collect())
+            })
+            .collect::<Result<Vec<Vec<usize>>, String>>()?;
 
-    fn calculate_bounding_box(faces: &Vec<Face>) -> Cube {
-        let mut min_bounds = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
-        let mut max_bounds = Point3::new(-f64::INFINITY, -f64::INFINITY, -f64::INFINITY);
+        Self::from_indexed(vertices, &faces)
+    }
 
-        for face in faces {
-            for vertex in &face.vertices {
-                let p = vertex.point;
+    /// Returns the faces of the mesh split into fans of triangles.
+    pub fn triangles(&self) -> Vec<[Point3; 3]> {
+        self.faces
+            .iter()
+            .flat_map(|f| {
+                let a = f.vertices[0].point;
+                f.vertices
+                    .windows(2)
+                    .skip(1)
+                    .map(move |w| [a, w[0].point, w[1].point])
+            })
+            .collect()
+    }
 
-                if p.x < min_bounds.x {
-                    min_bounds.x = p.x
-                }
-                if p.x > max_bounds.x {
-                    max_bounds.x = p.x
-                }
+    /// Builds a mesh out of a shared vertex list and faces that index into it. Any vertex
+    /// without a normal gets the area weighted average of the normals of the faces around it.
+    pub fn from_indexed(mut vertices: Vec<Vertex>, faces: &[Vec<usize>]) -> Result<Self, String> {
+        if let Some(&i) = faces.iter().flatten().find(|&&i| i >= vertices.len()) {
+            return Err(format!("Vertex index {} out of range", i));
+        }
 
-                if p.y < min_bounds.y {
-                    min_bounds.y = p.y
-                }
-                if p.y > max_bounds.y {
-                    max_bounds.y = p.y
+        if vertices.iter().any(|v| v.normal == Vector3::ZERO) {
+            let mut normals = vec![Vector3::ZERO; vertices.len()];
+            for face in faces {
+                let points = face.iter().map(|&i| vertices[i].point).collect::<Vec<_>>();
+                let n = Face::area_weighted_normal(&points);
+                for &i in face {
+                    normals[i] = normals[i] + n;
                 }
+            }
 
-                if p.z < min_bounds.z {
-                    min_bounds.z = p.z
-                }
-                if p.z > max_bounds.z {
-                    max_bounds.z = p.z
+            for (vertex, normal) in vertices.iter_mut().zip(normals) {
+                if vertex.normal == Vector3::ZERO && normal.magnitude() > EPSILON {
+                    vertex.normal = normal.normalize();
                 }
             }
         }
 
-        Cube::new(min_bounds, max_bounds)
+        let faces = faces
+            .iter()
+            .map(|f| Face::try_from(f.iter().map(|&i| vertices[i]).collect::<Vec<Vertex>>()))
+            .collect::<Result<Vec<Face>, String>>()?;
+
+        Ok(Self::from(faces))
+    }
+}
+
+fn ply_scalar(element: &DefaultElement, key: &str) -> Option<f64> {
+    match element.get(key)? {
+        Property::Float(val) => Some((*val).into()),
+        Property::Double(val) => Some(*val),
+        Property::Char(val) => Some((*val).into()),
+        Property::UChar(val) => Some((*val).into()),
+        Property::Short(val) => Some((*val).into()),
+        Property::UShort(val) => Some((*val).into()),
+        Property::Int(val) => Some((*val).into()),
+        Property::UInt(val) => Some((*val).into()),
+        _ => None,
     }
 }
 
+fn ply_indices(element: &DefaultElement) -> Result<Vec<usize>, String> {
+    let property = element
+        .get("vertex_indices")
+        .or_else(|| element.get("vertex_index"))
+        .ok_or_else(|| "Face without vertex indices".to_string())?;
+
+    let indices = match property {
+        Property::ListChar(x) => x.iter().map(|&i| i as usize).collect(),
+        Property::ListUChar(x) => x.iter().map(|&i| i as usize).collect(),
+        Property::ListShort(x) => x.iter().map(|&i| i as usize).collect(),
+        Property::ListUShort(x) => x.iter().map(|&i| i as usize).collect(),
+        Property::ListInt(x) => x.iter().map(|&i| i as usize).collect(),
+        Property::ListUInt(x) => x.iter().map(|&i| i as usize).collect(),
+        _ => return Err("Unsupported vertex index type".to_string()),
+    };
+
+    Ok(indices)
+}
+
 impl From<Vec<Face>> for Mesh {
     fn from(faces: Vec<Face>) -> Self {
-        let bounding_box = Self::calculate_bounding_box(&faces);
-
-        Self {
-            faces,
-            bounding_box,
-        }
+        let bvh = Bvh::new(
+            faces
+                .iter()
+                .enumerate()
+                .filter_map(|(i, f)| f.bounding_box().map(|b| (i, b))),
+        );
+
+        Self { faces, bvh }
     }
 }
 
 impl Intersect for Mesh {
     fn intersect(&self, ray: &Ray) -> Option<Intersection> {
-        if self.bounding_box.intersect(ray).is_none() {
+        self.bvh
+            .intersect(ray, f64::INFINITY, |i| self.faces[i].intersect(ray))
+            .map(|(_, intersection)| intersection)
+    }
+}
+
+impl Bounded for Mesh {
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        if self.bvh.is_empty() {
             return None;
         }
 
-        self.faces
-            .iter()
-            .filter_map(|x| x.intersect(ray))
-            .min_by(|i1, i2| i1.partial_cmp(i2).unwrap())
+        Some(self.bvh.bounding_box())
     }
 }
 
 impl Textured for Mesh {
-    fn to_texture_space(&self, _p: &Point3) -> Point2 {
-        Point2::default()
+    fn to_texture_space(&self, intersection: &Intersection) -> Point2 {
+        intersection.uv.unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intersect_smooth() {
+        let face = Face::try_from(vec![
+            Vertex::from(Point3::new(0.0, 0.0, 0.0)).with_normal(Vector3::new(-1.0, 0.0, -1.0)),
+            Vertex::from(Point3::new(1.0, 0.0, 0.0)).with_normal(Vector3::new(1.0, 0.0, -1.0)),
+            Vertex::from(Point3::new(0.0, 1.0, 0.0)).with_normal(Vector3::new(-1.0, 0.0, -1.0)),
+        ])
+        .unwrap();
+        assert_eq!(face.normal, -Vector3::K);
+
+        // halfway between the left and right edges the normal points straight at the ray
+        let ray = Ray::new(Point3::new(0.5, 0.25, -1.0), Vector3::K);
+        let intersection = face.intersect(&ray).unwrap();
+        assert!((intersection.normal - -Vector3::K).magnitude() < 1e-9);
+
+        // and matches the vertex normal at the vertex
+        let ray = Ray::new(Point3::new(1.0, 0.0, -1.0), Vector3::K);
+        let intersection = face.intersect(&ray).unwrap();
+        assert!(
+            (intersection.normal - Vector3::new(1.0, 0.0, -1.0).normalize()).magnitude() < 1e-9
+        );
+    }
+
+    #[test]
+    fn intersect_uv() {
+        let face = Face::try_from(vec![
+            Vertex::from(Point3::new(0.0, 0.0, 0.0)).with_uv(Point2::new(0.0, 0.0)),
+            Vertex::from(Point3::new(0.0, 2.0, 0.0)).with_uv(Point2::new(0.0, 1.0)),
+            Vertex::from(Point3::new(2.0, 2.0, 0.0)).with_uv(Point2::new(1.0, 1.0)),
+            Vertex::from(Point3::new(2.0, 0.0, 0.0)).with_uv(Point2::new(1.0, 0.0)),
+        ])
+        .unwrap();
+
+        let ray = Ray::new(Point3::new(0.5, 1.5, 1.0), -Vector3::K);
+        let uv = face.intersect(&ray).unwrap().uv.unwrap();
+        assert!((uv.x - 0.25).abs() < 1e-9 && (uv.y - 0.75).abs() < 1e-9);
+
+        let ray = Ray::new(Point3::new(1.5, 0.5, 1.0), -Vector3::K);
+        let uv = face.intersect(&ray).unwrap().uv.unwrap();
+        assert!((uv.x - 0.75).abs() < 1e-9 && (uv.y - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn from_ply_file_vertex_properties() {
+        let path = std::env::temp_dir().join("raytox_vertex_properties.ply");
+        std::fs::write(
+            &path,
+            "ply\n\
+             format ascii 1.0\n\
+             element vertex 3\n\
+             property float x\n\
+             property float y\n\
+             property float z\n\
+             property float nx\n\
+             property float ny\n\
+             property float nz\n\
+             property float s\n\
+             property float t\n\
+             element face 1\n\
+             property list uchar uint vertex_indices\n\
+             end_header\n\
+             0 0 0 0 0 1 0 0\n\
+             1 0 0 0 0 1 1 0\n\
+             0 1 0 0 0 1 0 1\n\
+             3 0 1 2\n",
+        )
+        .unwrap();
+
+        let mesh = Mesh::from_ply_file(path.to_str().unwrap()).unwrap();
+        let face = &mesh.faces[0];
+
+        assert_eq!(face.normal, Vector3::K);
+        assert!(face.vertices.iter().all(|v| v.normal == Vector3::K));
+        assert_eq!(face.vertices[0].uv, Some(Point2::new(0.0, 1.0)));
+        assert_eq!(face.vertices[2].uv, Some(Point2::new(0.0, 0.0)));
+    }
+
+    #[test]
+    fn from_indexed_vertex_normals() {
+        // a roof with its ridge along the z axis
+        let vertices = vec![
+            Point3::new(-1.0, 0.0, 0.0),
+            Point3::new(-1.0, 0.0, 1.0),
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 1.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 1.0),
+        ]
+        .into_iter()
+        .map(Vertex::from)
+        .collect();
+        let faces = vec![vec![0, 2, 3, 1], vec![2, 4, 5, 3]];
+
+        let mesh = Mesh::from_indexed(vertices, &faces).unwrap();
+        let left = &mesh.faces[0];
+        let right = &mesh.faces[1];
+
+        assert!((left.normal - Vector3::new(-1.0, 1.0, 0.0).normalize()).magnitude() < 1e-9);
+        assert!((right.normal - Vector3::new(1.0, 1.0, 0.0).normalize()).magnitude() < 1e-9);
+
+        // the ridge is shared, so its normal points straight up, the eaves keep their face normal
+        assert!((left.vertices[1].normal - Vector3::J).magnitude() < 1e-9);
+        assert!((left.vertices[0].normal - left.normal).magnitude() < 1e-9);
+        assert!((right.vertices[1].normal - right.normal).magnitude() < 1e-9);
+
+        let error = Mesh::from_indexed(vec![Vertex::default(); 3], &[vec![0, 1, 3]]);
+        assert_eq!(error.unwrap_err(), "Vertex index 3 out of range");
+    }
+
+    #[test]
+    fn intersect_matches_brute_force() {
+        let mesh = Mesh::from_ply_file("assets/teapot.ply").unwrap();
+
+        for i in 0..200 {
+            let i = i as f64;
+            let origin = Point3::new(
+                20.0 * (i * 0.1).cos(),
+                5.0 + (i * 0.3).sin(),
+                20.0 * (i * 0.1).sin(),
+            );
+            let target = Point3::new((i * 0.7).sin(), 1.5 + (i * 0.5).cos(), (i * 0.9).sin());
+            let ray = Ray::new(origin, (target - origin).normalize());
+
+            let expected = mesh
+                .faces
+                .iter()
+                .filter_map(|x| x.intersect(&ray))
+                .min_by(|i1, i2| i1.partial_cmp(i2).unwrap());
+
+            assert_eq!(mesh.intersect(&ray), expected);
+        }
     }
 }
@@ -1,8 +1,8 @@
 use std::f64::consts::PI;
 
-use crate::algebra::{Point2, Point3, Ray};
+use crate::algebra::{Point2, Point3, Ray, Vector3};
 
-use super::{Intersect, Intersection, Textured};
+use super::{Bounded, BoundingBox, Intersect, Intersection, Textured};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Sphere {
@@ -59,14 +59,10 @@ impl Intersect for Sphere {
                 return None;
             }
 
-            if t1 < 0.0 {
+            if t1 < 0.0 || (t2 >= 0.0 && t2 <= t1) {
                 t = t2;
-            } else if t2 < 0.0 {
-                t = t1;
-            } else if t1 < t2 {
-                t = t1;
             } else {
-                t = t2;
+                t = t1;
             }
         }
 
@@ -76,8 +72,16 @@ impl Intersect for Sphere {
     }
 }
 
+impl Bounded for Sphere {
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        let r = Vector3::new(self.radius, self.radius, self.radius);
+        Some(BoundingBox::new(self.center - r, self.center + r))
+    }
+}
+
 impl Textured for Sphere {
-    fn to_texture_space(&self, p: &Point3) -> Point2 {
+    fn to_texture_space(&self, intersection: &Intersection) -> Point2 {
+        let p = &intersection.position;
         let d = (self.center - *p).normalize();
         Point2::new(0.5 + d.z.atan2(d.x) / (2.0 * PI), 0.5 - d.y.asin() / PI)
     }
@@ -85,8 +89,6 @@ impl Textured for Sphere {
 
 #[cfg(test)]
 mod tests {
-    use crate::algebra::Vector3;
-
     use super::*;
 
     #[test]
@@ -0,0 +1,104 @@
+use crate::{
+    algebra::{Point3, Ray, Vector3, EPSILON},
+    color::Color,
+    geometry::Intersection,
+    sampling,
+    scene::Scene,
+};
+
+use super::Integrator;
+
+/// Ambient occlusion: the fraction of the hemisphere above a point that is not blocked by
+/// nearby geometry casting shadows, estimated with cosine distributed rays.
+///
+/// As an integrator it renders the unoccluded fraction at the primary hits as a grayscale
+/// image, with white where the camera sees the background. It can also darken the ambient term
+/// of the [`Whitted`](super::Whitted) integrator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AmbientOcclusion {
+    /// Number of rays cast per point.
+    pub samples: u32,
+    /// Geometry further away than this doesn't occlude.
+    pub max_distance: f64,
+}
+
+impl AmbientOcclusion {
+    pub fn new(samples: u32, max_distance: f64) -> Self {
+        Self {
+            samples,
+            max_distance,
+        }
+    }
+
+    /// Returns the fraction of the hemisphere around the unit normal `n` at `point` that is
+    /// unoccluded at `time`, 1 meaning nothing is in the way.
+    pub fn visibility(&self, scene: &Scene, point: &Point3, n: &Vector3, time: f64) -> f64 {
+        if self.samples == 0 {
+            return 1.0;
+        }
+
+        let origin = *point + EPSILON * *n;
+        let unoccluded = (0..self.samples)
+            .filter(|_| {
+                let ray =
+                    Ray::new(origin, sampling::cosine_power_direction(n, 1.0)).with_time(time);
+                !scene.occluded(&ray, self.max_distance)
+            })
+            .count();
+
+        unoccluded as f64 / self.samples as f64
+    }
+}
+
+impl Default for AmbientOcclusion {
+    fn default() -> Self {
+        Self::new(16, f64::INFINITY)
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(&self, scene: &Scene, ray: &Ray) -> Color {
+        match scene.closest_intersection(ray) {
+            Some((
+                _,
+                Intersection {
+                    position, normal, ..
+                },
+            )) => {
+                let n = if ray.dir.dot(&normal) < 0.0 {
+                    normal
+                } else {
+                    -normal
+                };
+                Color::WHITE * self.visibility(scene, &position, &n, ray.time)
+            }
+            None => Color::WHITE,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{camera::Camera, entity::Entity, geometry::Plane};
+
+    #[test]
+    fn occluded_corner() {
+        let mut scene = Scene::new(1, 1, Camera::new(Point3::O), Color::BLACK);
+        scene.add_entity(Entity::from(Plane::new(Point3::O, Vector3::J)));
+        scene.add_entity(Entity::from(Plane::new(Point3::O, Vector3::I)));
+
+        let ao = AmbientOcclusion::new(2000, 10.0);
+        let open = ao.visibility(&scene, &Point3::new(100.0, 0.0, 0.0), &Vector3::J, 0.0);
+        let corner = ao.visibility(&scene, &Point3::new(0.1, 0.0, 0.0), &Vector3::J, 0.0);
+        assert_eq!(open, 1.0);
+        // the wall blocks nearly every direction going towards it, half of the hemisphere
+        assert!((corner - 0.5).abs() < 0.05, "{}", corner);
+
+        let near = AmbientOcclusion::new(100, 0.01);
+        assert_eq!(
+            near.visibility(&scene, &Point3::new(0.1, 0.0, 0.0), &Vector3::J, 0.0),
+            1.0
+        );
+    }
+}
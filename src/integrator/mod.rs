@@ -0,0 +1,61 @@
+mod ambient_occlusion;
+mod path;
+mod whitted;
+
+pub use ambient_occlusion::AmbientOcclusion;
+pub use path::PathTracer;
+pub use whitted::Whitted;
+
+use std::fmt::Debug;
+
+use crate::{algebra::Ray, color::Color, scene::Scene};
+
+/// Computes the radiance arriving at the origin of a ray from its direction, which is what a
+/// camera sample sees.
+///
+/// Integrators are shared by every thread rendering the scene.
+pub trait Integrator: Debug + Send + Sync {
+    fn radiance(&self, scene: &Scene, ray: &Ray) -> Color;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        algebra::Point3,
+        camera::{Camera, Projection},
+        entity::Entity,
+        geometry::Sphere,
+    };
+
+    /// Debug integrator showing the normals of the surfaces.
+    #[derive(Debug)]
+    struct Normals;
+
+    impl Integrator for Normals {
+        fn radiance(&self, scene: &Scene, ray: &Ray) -> Color {
+            match scene.closest_intersection(ray) {
+                Some((_, intersection)) => {
+                    let n = intersection.normal;
+                    Color::new(n.x, n.y, n.z) * 0.5 + Color::WHITE * 0.5
+                }
+                None => Color::BLACK,
+            }
+        }
+    }
+
+    #[test]
+    fn custom_integrator() {
+        let mut camera = Camera::new(Point3::new(0.0, 0.0, -10.0))
+            .with_projection(Projection::Perspective { fov: 1.0 });
+        camera.look_at(Point3::O);
+        let mut scene = Scene::new(9, 9, camera, Color::WHITE).with_integrator(Normals);
+        scene.add_entity(Entity::from(Sphere::new(Point3::O, 2.0)));
+
+        let film = scene.render_film();
+
+        // the center of the sphere faces the camera, with a normal of -K
+        assert_eq!(film.pixel(4, 4), Color::new(0.5, 0.5, 0.0));
+        assert_eq!(film.pixel(0, 0), Color::BLACK);
+    }
+}
@@ -0,0 +1,328 @@
+use std::f64::consts::PI;
+
+use rand::random;
+
+use crate::{
+    algebra::{Point3, Ray, Vector3, EPSILON},
+    color::Color,
+    geometry::{Intersection, Textured},
+    light::{Illuminate, LightSample},
+    material::fresnel,
+    sampling,
+    scene::Scene,
+};
+
+use super::Integrator;
+
+/// Unidirectional Monte Carlo path tracer, with next event estimation towards the lights and
+/// Russian roulette to end paths.
+///
+/// Mirror reflection and transparency are handled as perfectly specular events, with the event
+/// picked at random according to their weights. The background acts as a light surrounding the
+/// scene, sampled directly when it is an environment with light samples, and emissive surfaces
+/// light the scene through the paths that happen to hit them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathTracer {
+    /// Hard limit on the number of bounces, Russian roulette normally ends paths much earlier.
+    pub max_depth: u32,
+    /// Number of bounces before paths start being terminated by Russian roulette.
+    pub roulette_depth: u32,
+}
+
+impl PathTracer {
+    pub fn new(max_depth: u32) -> Self {
+        Self {
+            max_depth,
+            roulette_depth: 3,
+        }
+    }
+
+    pub fn with_roulette_depth(mut self, roulette_depth: u32) -> Self {
+        self.roulette_depth = roulette_depth;
+        self
+    }
+}
+
+/// Reflectance of an opaque surface: a Lambertian diffuse lobe plus a normalized Phong lobe
+/// around the mirror direction.
+struct SurfaceBrdf {
+    kd: Color,
+    ks: Color,
+    shininess: f64,
+    n: Vector3,
+    // mirror direction of the outgoing direction
+    r: Vector3,
+    // probability of sampling the diffuse lobe
+    diffuse_weight: f64,
+}
+
+impl SurfaceBrdf {
+    fn new(kd: Color, ks: Color, shininess: f64, n: Vector3, wo: Vector3) -> Self {
+        // Phong materials are free to reflect more light than they receive, scale them down
+        // so that paths don't gain energy
+        let albedo = kd + ks;
+        let max = albedo.r.max(albedo.g).max(albedo.b);
+        let (kd, ks) = if max > 1.0 {
+            (kd * (1.0 / max), ks * (1.0 / max))
+        } else {
+            (kd, ks)
+        };
+
+        let (ld, ls) = (kd.luminance(), ks.luminance());
+        let diffuse_weight = if ld + ls > 0.0 { ld / (ld + ls) } else { 1.0 };
+
+        Self {
+            kd,
+            ks,
+            shininess,
+            n,
+            r: (-wo).reflect(&n),
+            diffuse_weight,
+        }
+    }
+
+    fn is_black(&self) -> bool {
+        self.kd == Color::BLACK && self.ks == Color::BLACK
+    }
+
+    fn evaluate(&self, wi: &Vector3) -> Color {
+        let cos_a = self.r.dot(wi).max(0.0);
+        let glossy = (self.shininess + 2.0) / (2.0 * PI) * cos_a.powf(self.shininess);
+        self.kd * (1.0 / PI) + self.ks * glossy
+    }
+
+    fn pdf(&self, wi: &Vector3) -> f64 {
+        self.diffuse_weight * sampling::cosine_power_pdf(self.n.dot(wi), 1.0)
+            + (1.0 - self.diffuse_weight)
+                * sampling::cosine_power_pdf(self.r.dot(wi), self.shininess)
+    }
+
+    /// Samples an incoming direction, returning it along with the BRDF times the cosine term
+    /// divided by the pdf of the direction.
+    fn sample(&self) -> Option<(Vector3, Color)> {
+        let wi = if random::<f64>() < self.diffuse_weight {
+            sampling::cosine_power_direction(&self.n, 1.0)
+        } else {
+            sampling::cosine_power_direction(&self.r, self.shininess)
+        };
+
+        let cos = self.n.dot(&wi);
+        let pdf = self.pdf(&wi);
+        if cos <= 0.0 || pdf <= 0.0 {
+            return None;
+        }
+
+        Some((wi, self.evaluate(&wi) * (cos / pdf)))
+    }
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        Self::new(64)
+    }
+}
+
+impl Integrator for PathTracer {
+    fn radiance(&self, scene: &Scene, ray: &Ray) -> Color {
+        let mut ray = *ray;
+        let mut throughput = Color::WHITE;
+        let mut radiance = Color::BLACK;
+        // density the last direction was sampled with by the BRDF, none for camera rays and
+        // specular events
+        let mut brdf_pdf = None;
+
+        for depth in 0..self.max_depth {
+            let (entity, intersection) = match scene.closest_intersection(&ray) {
+                Some(hit) => hit,
+                None => {
+                    let background = scene.background.radiance(&ray.dir);
+                    let weight = match (scene.background.light(), brdf_pdf) {
+                        // the environment was also sampled directly at the last bounce
+                        (Some(env), Some(pdf)) => {
+                            power_heuristic(pdf, env.light_samples as f64 * env.pdf(&ray.dir))
+                        }
+                        _ => 1.0,
+                    };
+                    radiance = radiance + throughput * background * weight;
+                    break;
+                }
+            };
+
+            let Intersection {
+                position, normal, ..
+            } = intersection;
+            let material = entity.material();
+            radiance = radiance + throughput * material.emission;
+
+            // shade both sides of surfaces, with the normal facing the incoming ray
+            let entering = ray.dir.dot(&normal) < 0.0;
+            let n = if entering { normal } else { -normal };
+
+            brdf_pdf = None;
+            let event = random::<f64>();
+            let transparency = material.transparency;
+            let reflectance = (1.0 - transparency) * material.reflectance;
+
+            ray = if event < transparency {
+                let ior = material.refractive_index;
+                let (n1, n2) = if entering { (1.0, ior) } else { (ior, 1.0) };
+                let kr = fresnel(-ray.dir.dot(&n), n1, n2);
+
+                match ray.dir.refract(&n, n1 / n2) {
+                    Some(t) if random::<f64>() >= kr => ray.spawn(position - EPSILON * n, t),
+                    _ => ray.spawn(position + EPSILON * n, ray.dir.reflect(&n)),
+                }
+            } else if event < transparency + reflectance {
+                ray.spawn(position + EPSILON * n, ray.dir.reflect(&n))
+            } else {
+                let uv = entity.to_texture_space(&intersection);
+                let brdf = SurfaceBrdf::new(
+                    material.diffuse.color_at(&uv),
+                    material.specular.color_at(&uv),
+                    material.shininess,
+                    n,
+                    -ray.dir,
+                );
+                if brdf.is_black() {
+                    break;
+                }
+
+                let origin = position + EPSILON * n;
+                radiance = radiance + throughput * direct_lighting(scene, &origin, ray.time, &brdf);
+
+                match brdf.sample() {
+                    Some((wi, weight)) => {
+                        throughput = throughput * weight;
+                        brdf_pdf = Some(brdf.pdf(&wi));
+                        ray.spawn(origin, wi)
+                    }
+                    None => break,
+                }
+            };
+
+            if depth >= self.roulette_depth {
+                let survival = throughput.r.max(throughput.g).max(throughput.b).min(0.95);
+                if random::<f64>() >= survival {
+                    break;
+                }
+                throughput = throughput * (1.0 / survival);
+            }
+        }
+
+        radiance
+    }
+}
+
+/// Light arriving directly from every light and reflected by the surface.
+///
+/// The intensity of the lights is scaled by PI, so that their direct diffuse lighting matches
+/// the Whitted renderer. An environment lighting the scene is importance sampled, and combined
+/// with the paths escaping the scene by multiple importance sampling.
+fn direct_lighting(scene: &Scene, origin: &Point3, time: f64, brdf: &SurfaceBrdf) -> Color {
+    let lights: Color = scene
+        .lights
+        .iter()
+        .map(|light| {
+            let LightSample {
+                direction: wi,
+                distance,
+                intensity,
+            } = light.illuminate(origin);
+
+            let cos = brdf.n.dot(&wi);
+            if cos <= 0.0 || scene.occluded(&Ray::new(*origin, wi).with_time(time), distance) {
+                return Color::BLACK;
+            }
+
+            brdf.evaluate(&wi) * light.diffuse() * (cos * PI * intensity)
+        })
+        .sum();
+
+    let env = match scene.background.light() {
+        Some(env) => env,
+        None => return lights,
+    };
+
+    let count = env.light_samples as f64;
+    let environment: Color = sampling::stratified_2d(env.light_samples)
+        .iter()
+        .map(|uv| {
+            let (wi, pdf) = env.sample(uv);
+            let cos = brdf.n.dot(&wi);
+            if cos <= 0.0
+                || pdf <= 0.0
+                || scene.occluded(&Ray::new(*origin, wi).with_time(time), f64::INFINITY)
+            {
+                return Color::BLACK;
+            }
+
+            let weight = power_heuristic(count * pdf, brdf.pdf(&wi));
+            brdf.evaluate(&wi) * env.radiance(&wi) * (cos * weight / (count * pdf))
+        })
+        .sum();
+
+    lights + environment
+}
+
+/// Weight of a sample taken with density `a`, when a sample could also have been taken with
+/// density `b`.
+fn power_heuristic(a: f64, b: f64) -> f64 {
+    if a == 0.0 {
+        return 0.0;
+    }
+    a * a / (a * a + b * b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brdf_sampling_is_consistent() {
+        let n = Vector3::J;
+        let wo = Vector3::new(1.0, 1.0, 0.0).normalize();
+        let brdf = SurfaceBrdf::new(Color::WHITE * 0.5, Color::WHITE * 0.3, 10.0, n, wo);
+
+        for _ in 0..100 {
+            if let Some((wi, weight)) = brdf.sample() {
+                let expected = brdf.evaluate(&wi) * (n.dot(&wi) / brdf.pdf(&wi));
+                assert!((weight - expected).luminance().abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn lit_by_background() {
+        use crate::{camera::Camera, entity::Entity, geometry::Plane, material::Phong};
+
+        let mut scene = Scene::new(1, 1, Camera::new(Point3::O), Color::WHITE);
+        scene.add_entity(
+            Entity::from(Plane::new(Point3::O, Vector3::J)).with_material(Phong::new(
+                Color::BLACK,
+                Color::WHITE * 0.5,
+                Color::WHITE * 0.25,
+                10.0,
+            )),
+        );
+
+        // an infinite plane under a uniform sky reflects its full albedo
+        let ray = Ray::new(
+            Point3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.3, -1.0, 0.0).normalize(),
+        );
+        let n = 20000;
+        let mean = (0..n)
+            .map(|_| PathTracer::default().radiance(&scene, &ray))
+            .sum::<Color>()
+            * (1.0 / n as f64);
+
+        assert!(mean.r > 0.65 && mean.r < 0.75, "{:?}", mean);
+    }
+
+    #[test]
+    fn brdf_conserves_energy() {
+        let brdf = SurfaceBrdf::new(Color::BLUE, Color::WHITE, 20.0, Vector3::J, Vector3::J);
+
+        assert_eq!(brdf.kd + brdf.ks, Color::new(0.5, 0.5, 1.0));
+    }
+}
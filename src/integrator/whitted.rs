@@ -0,0 +1,223 @@
+use std::f64::consts::PI;
+
+use crate::{
+    algebra::{Point3, Ray, Vector3, EPSILON},
+    background::Environment,
+    color::Color,
+    geometry::{Intersection, Textured},
+    light::{Illuminate, LightSample},
+    material::fresnel,
+    sampling,
+    scene::Scene,
+};
+
+use super::{AmbientOcclusion, Integrator};
+
+/// Whitted style ray tracer: Phong shading of every light with shadow rays, plus recursive
+/// mirror reflection and refraction. Emissive entities are sampled like area lights. It has no
+/// indirect lighting, but it is fast and noise free, which makes it a good preview.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Whitted {
+    /// Maximum number of rays traced along a path, the background is returned past it.
+    pub max_depth: u32,
+    /// Darkens the ambient term by the ambient occlusion at every hit, when set.
+    pub ambient_occlusion: Option<AmbientOcclusion>,
+}
+
+impl Whitted {
+    pub fn new(max_depth: u32) -> Self {
+        Self {
+            max_depth,
+            ambient_occlusion: None,
+        }
+    }
+
+    pub fn with_ambient_occlusion(mut self, ambient_occlusion: AmbientOcclusion) -> Self {
+        self.ambient_occlusion = Some(ambient_occlusion);
+        self
+    }
+
+    fn trace(&self, scene: &Scene, ray: Ray, depth: u32) -> Color {
+        if depth == 0 {
+            return scene.background.radiance(&ray.dir);
+        }
+
+        match scene.closest_intersection(&ray) {
+            Some((entity, intersection)) => {
+                let Intersection {
+                    position: intersect_point,
+                    normal,
+                    ..
+                } = intersection;
+                let material = entity.material();
+                let uv = entity.to_texture_space(&intersection);
+
+                let ka = material.ambient.color_at(&uv);
+                let kd = material.diffuse.color_at(&uv);
+                let ks = material.specular.color_at(&uv);
+                let alpha = material.shininess;
+                let reflectance = material.reflectance;
+
+                let n = normal;
+                let v = -ray.dir;
+                let offset_position = intersect_point + EPSILON * normal;
+
+                let ambient_visibility = match &self.ambient_occlusion {
+                    Some(ao) => {
+                        let facing = if ray.dir.dot(&normal) < 0.0 {
+                            normal
+                        } else {
+                            -normal
+                        };
+                        ao.visibility(scene, &intersect_point, &facing, ray.time)
+                    }
+                    None => 1.0,
+                };
+
+                let lighting: Color = scene
+                    .lights
+                    .iter()
+                    .chain(scene.emitters())
+                    .flat_map(|light| {
+                        light
+                            .samples(&intersect_point)
+                            .into_iter()
+                            .map(move |sample| (light, sample))
+                    })
+                    .map(|(light, sample)| {
+                        let LightSample {
+                            direction: l,
+                            distance,
+                            intensity,
+                        } = sample;
+                        let ia = light.ambient();
+                        let id = light.diffuse();
+                        let is = light.specular();
+
+                        let r = 2.0 * l.dot(&n) * n - l;
+
+                        let mut color = ka * ia * (intensity * ambient_visibility);
+
+                        let shadow_ray = ray.spawn(offset_position, l);
+                        if scene.occluded(&shadow_ray, distance) {
+                            return color;
+                        }
+
+                        if l.dot(&n) > 0.0 {
+                            color = color + kd * l.dot(&n) * id * intensity;
+                        }
+
+                        if r.dot(&v) > 0.0 {
+                            color = color + ks * r.dot(&v).powf(alpha) * is * intensity;
+                        }
+
+                        color
+                    })
+                    .sum();
+                let lighting = match scene.background.light() {
+                    Some(env) => {
+                        lighting
+                            + environment_lighting(
+                                scene,
+                                env,
+                                &offset_position,
+                                ray.time,
+                                &n,
+                                &v,
+                                kd,
+                                ks,
+                                alpha,
+                            )
+                    }
+                    None => lighting,
+                };
+                let local_color = material.emission + lighting;
+
+                let transparency = material.transparency;
+                if reflectance == 0.0 && transparency == 0.0 {
+                    return local_color;
+                }
+
+                // the surface is entered when the ray hits it against its normal, which also
+                // holds for inward facing normals like the ones of a flipped cube
+                let entering = ray.dir.dot(&normal) < 0.0;
+                let facing = if entering { normal } else { -normal };
+
+                let reflected_ray =
+                    ray.spawn(intersect_point + EPSILON * facing, ray.dir.reflect(&facing));
+                let reflected_color = self.trace(scene, reflected_ray, depth - 1);
+                let opaque_color =
+                    reflected_color * reflectance + local_color * (1.0 - reflectance);
+
+                if transparency == 0.0 {
+                    return opaque_color;
+                }
+
+                let ior = material.refractive_index;
+                let (n1, n2) = if entering { (1.0, ior) } else { (ior, 1.0) };
+                let kr = fresnel(-ray.dir.dot(&facing), n1, n2);
+
+                let transmitted_color = match ray.dir.refract(&facing, n1 / n2) {
+                    Some(t) if kr < 1.0 => {
+                        let refracted_ray = ray.spawn(intersect_point - EPSILON * facing, t);
+                        self.trace(scene, refracted_ray, depth - 1)
+                    }
+                    _ => Color::BLACK,
+                };
+                let dielectric_color = reflected_color * kr + transmitted_color * (1.0 - kr);
+
+                dielectric_color * transparency + opaque_color * (1.0 - transparency)
+            }
+            None => scene.background.radiance(&ray.dir),
+        }
+    }
+}
+
+impl Default for Whitted {
+    fn default() -> Self {
+        Self::new(5)
+    }
+}
+
+impl Integrator for Whitted {
+    fn radiance(&self, scene: &Scene, ray: &Ray) -> Color {
+        self.trace(scene, *ray, self.max_depth)
+    }
+}
+
+/// Phong shading of the light arriving from an environment, estimated with importance sampled
+/// shadow rays.
+///
+/// Light from a solid angle `dw` with radiance `L` contributes like a light of intensity
+/// `L * dw / PI` would, the same convention the path tracer uses for the other lights.
+#[allow(clippy::too_many_arguments)]
+fn environment_lighting(
+    scene: &Scene,
+    env: &Environment,
+    origin: &Point3,
+    time: f64,
+    n: &Vector3,
+    v: &Vector3,
+    kd: Color,
+    ks: Color,
+    alpha: f64,
+) -> Color {
+    let count = env.light_samples as f64;
+    sampling::stratified_2d(env.light_samples)
+        .iter()
+        .map(|uv| {
+            let (l, pdf) = env.sample(uv);
+            let cos = l.dot(n);
+            if cos <= 0.0
+                || pdf <= 0.0
+                || scene.occluded(&Ray::new(*origin, l).with_time(time), f64::INFINITY)
+            {
+                return Color::BLACK;
+            }
+
+            let r = 2.0 * cos * *n - l;
+            let specular = r.dot(v).max(0.0).powf(alpha);
+            (kd * cos + ks * specular) * env.radiance(&l) * (1.0 / (PI * pdf * count))
+        })
+        .sum()
+}
@@ -75,7 +75,7 @@ impl InteractiveWindow {
                 self.handle_update(args.dt);
             }
 
-            if let Some(_) = e.render_args() {
+            if e.render_args().is_some() {
                 self.handle_render(e);
             }
         }
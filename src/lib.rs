@@ -1,10 +1,18 @@
 pub mod algebra;
+pub mod animation;
+pub mod background;
 pub mod camera;
 pub mod color;
 pub mod entity;
+pub mod film;
 pub mod geometry;
+pub mod integrator;
 pub mod light;
 pub mod material;
+pub mod obj;
+pub mod sampling;
 pub mod scene;
+mod scene_file;
 pub mod texture;
+pub mod tone_map;
 pub mod interactive;
@@ -0,0 +1,162 @@
+use std::f64::consts::PI;
+
+use rand::random;
+
+use crate::algebra::{Point2, Point3, Vector3, EPSILON};
+use crate::color::Color;
+use crate::sampling;
+
+use super::{Illuminate, LightSample};
+
+/// Surface of an area light.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AreaShape {
+    /// Parallelogram spanned by the edges `u` and `v` around its center, emitting on the side
+    /// of `u × v`.
+    Rectangle {
+        center: Point3,
+        u: Vector3,
+        v: Vector3,
+    },
+    /// Disk emitting on the side of its unit normal.
+    Disk {
+        center: Point3,
+        normal: Vector3,
+        radius: f64,
+    },
+    /// Sphere emitting equally in every direction.
+    Sphere { center: Point3, radius: f64 },
+}
+
+impl AreaShape {
+    /// Maps a position in the unit square onto the part of the surface that can light `p`,
+    /// returning the point on the surface, its outward normal and the factor correcting for
+    /// the surface not being sampled in full.
+    fn sample(&self, p: &Point3, uv: &Point2) -> (Point3, Vector3, f64) {
+        match *self {
+            Self::Rectangle { center, u, v } => {
+                let point = center + (uv.x - 0.5) * u + (uv.y - 0.5) * v;
+                (point, u.cross(&v).normalize(), 1.0)
+            }
+            Self::Disk {
+                center,
+                normal,
+                radius,
+            } => {
+                let (a, b) = sampling::orthonormal_basis(&normal);
+                let r = radius * uv.x.sqrt();
+                let phi = 2.0 * PI * uv.y;
+                let point = center + r * phi.cos() * a + r * phi.sin() * b;
+                (point, normal, 1.0)
+            }
+            Self::Sphere { center, radius } => {
+                // only the hemisphere facing p is sampled, and as the average cosine over it is
+                // 1/2 the samples count double for the sphere to be as bright as a point light.
+                // No hemisphere faces the center, from where the whole sphere is sampled.
+                let offset = *p - center;
+                let (axis, cos_theta, weight) = if offset.magnitude() > EPSILON {
+                    (offset.normalize(), uv.x, 2.0)
+                } else {
+                    (Vector3::J, 1.0 - 2.0 * uv.x, 1.0)
+                };
+                let (a, b) = sampling::orthonormal_basis(&axis);
+                let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+                let phi = 2.0 * PI * uv.y;
+                let normal =
+                    sin_theta * phi.cos() * a + sin_theta * phi.sin() * b + cos_theta * axis;
+                (center + radius * normal, normal, weight)
+            }
+        }
+    }
+}
+
+/// Light emitted from the surface of a shape, casting soft shadows.
+///
+/// The surface is a Lambertian emitter with `intensity` being the total intensity along its
+/// normal, so from far away the light is as bright as a point light of the same intensity.
+/// Shading points split the light into `samples` stratified samples across the surface, each
+/// with its own shadow ray.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AreaLight {
+    pub shape: AreaShape,
+    pub ambient: Color,
+    pub diffuse: Color,
+    pub specular: Color,
+    pub intensity: f64,
+    /// Number of samples taken across the surface at every shading point.
+    pub samples: u32,
+}
+
+impl AreaLight {
+    pub fn new(
+        shape: AreaShape,
+        ambient: Color,
+        diffuse: Color,
+        specular: Color,
+        intensity: f64,
+    ) -> Self {
+        let shape = match shape {
+            AreaShape::Disk {
+                center,
+                normal,
+                radius,
+            } => AreaShape::Disk {
+                center,
+                normal: normal.normalize(),
+                radius,
+            },
+            shape => shape,
+        };
+
+        Self {
+            shape,
+            ambient,
+            diffuse,
+            specular,
+            intensity,
+            samples: 16,
+        }
+    }
+
+    pub fn with_samples(mut self, samples: u32) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    fn sample_at(&self, p: &Point3, uv: &Point2) -> LightSample {
+        let (point, normal, weight) = self.shape.sample(p, uv);
+        let to_light = point - *p;
+        let distance = to_light.magnitude();
+        let direction = to_light / distance;
+        let cos_light = (-direction).dot(&normal).max(0.0);
+
+        LightSample {
+            direction,
+            // stop shadow rays just short of the surface, which may belong to an emissive
+            // entity
+            distance: distance - EPSILON,
+            intensity: self.intensity * weight * cos_light / (distance * distance),
+        }
+    }
+}
+
+impl Illuminate for AreaLight {
+    /// Returns a single sample at a random position on the surface.
+    fn illuminate(&self, p: &Point3) -> LightSample {
+        self.sample_at(p, &Point2::new(random(), random()))
+    }
+
+    fn samples(&self, p: &Point3) -> Vec<LightSample> {
+        let n = self.samples as f64;
+        sampling::stratified_2d(self.samples)
+            .iter()
+            .map(|uv| {
+                let sample = self.sample_at(p, uv);
+                LightSample {
+                    intensity: sample.intensity / n,
+                    ..sample
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,44 @@
+use crate::algebra::{Point3, Vector3};
+use crate::color::Color;
+
+use super::{Illuminate, LightSample};
+
+/// Light coming from infinitely far away along a single direction, like the sun. Its intensity
+/// does not fall off with distance.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct DirectionalLight {
+    /// Direction the light travels in.
+    pub direction: Vector3,
+    pub ambient: Color,
+    pub diffuse: Color,
+    pub specular: Color,
+    pub intensity: f64,
+}
+
+impl DirectionalLight {
+    pub fn new(
+        direction: Vector3,
+        ambient: Color,
+        diffuse: Color,
+        specular: Color,
+        intensity: f64,
+    ) -> Self {
+        Self {
+            direction: direction.normalize(),
+            ambient,
+            diffuse,
+            specular,
+            intensity,
+        }
+    }
+}
+
+impl Illuminate for DirectionalLight {
+    fn illuminate(&self, _p: &Point3) -> LightSample {
+        LightSample {
+            direction: -self.direction,
+            distance: f64::INFINITY,
+            intensity: self.intensity,
+        }
+    }
+}
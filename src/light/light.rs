@@ -0,0 +1,374 @@
+use crate::algebra::{Matrix4, Point3, Vector3};
+use crate::color::Color;
+use crate::entity::Transformable;
+use crate::geometry::Axis;
+use crate::sampling;
+
+use super::{AreaLight, AreaShape, DirectionalLight, MeshLight, PointLight, SpotLight};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Light {
+    Point(PointLight),
+    Directional(DirectionalLight),
+    Spot(SpotLight),
+    Area(AreaLight),
+    Mesh(MeshLight),
+}
+
+/// Light arriving at a point from a light source.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightSample {
+    /// Unit vector from the point towards the light.
+    pub direction: Vector3,
+    /// Distance to the light along `direction`, shadow rays are blocked by anything closer. It
+    /// is infinite for lights without a position.
+    pub distance: f64,
+    /// Intensity of the light at the point, after any falloff.
+    pub intensity: f64,
+}
+
+pub trait Illuminate {
+    /// Returns the light arriving at the given point in world space, ignoring shadows.
+    ///
+    /// Lights with an area return the light coming from a random point on their surface.
+    fn illuminate(&self, p: &Point3) -> LightSample;
+
+    /// Returns the light arriving at the given point split into samples, each needing its own
+    /// shadow ray, whose contributions add up to the total. Lights without an area have a single
+    /// sample.
+    fn samples(&self, p: &Point3) -> Vec<LightSample> {
+        vec![self.illuminate(p)]
+    }
+}
+
+impl Light {
+    pub fn ambient(&self) -> Color {
+        match self {
+            Self::Point(x) => x.ambient,
+            Self::Directional(x) => x.ambient,
+            Self::Spot(x) => x.ambient,
+            Self::Area(x) => x.ambient,
+            Self::Mesh(_) => Color::BLACK,
+        }
+    }
+
+    pub fn diffuse(&self) -> Color {
+        match self {
+            Self::Point(x) => x.diffuse,
+            Self::Directional(x) => x.diffuse,
+            Self::Spot(x) => x.diffuse,
+            Self::Area(x) => x.diffuse,
+            Self::Mesh(x) => x.radiance,
+        }
+    }
+
+    pub fn specular(&self) -> Color {
+        match self {
+            Self::Point(x) => x.specular,
+            Self::Directional(x) => x.specular,
+            Self::Spot(x) => x.specular,
+            Self::Area(x) => x.specular,
+            Self::Mesh(x) => x.radiance,
+        }
+    }
+}
+
+/// Lights are moved as a whole, with the sizes of area lights scaled by how much the transform
+/// stretches them along one of their axes, as they are assumed to be scaled uniformly.
+impl Transformable for Light {
+    fn translate(self, translation: Vector3) -> Self {
+        self.transform(Matrix4::translation(translation))
+    }
+
+    fn rotate(self, axis: Axis, degrees: f64) -> Self {
+        self.transform(Matrix4::rotation(axis, degrees))
+    }
+
+    fn scale(self, scale: Vector3) -> Self {
+        self.transform(Matrix4::scaling(scale))
+    }
+
+    fn transform(self, transform: Matrix4) -> Self {
+        match self {
+            Self::Point(x) => Self::Point(PointLight {
+                position: x.position.transform(transform),
+                ..x
+            }),
+            Self::Directional(x) => Self::Directional(DirectionalLight {
+                direction: x.direction.transform(transform).normalize(),
+                ..x
+            }),
+            Self::Spot(x) => Self::Spot(SpotLight {
+                position: x.position.transform(transform),
+                direction: x.direction.transform(transform).normalize(),
+                ..x
+            }),
+            Self::Area(x) => {
+                let shape = match x.shape {
+                    AreaShape::Rectangle { center, u, v } => AreaShape::Rectangle {
+                        center: center.transform(transform),
+                        u: u.transform(transform),
+                        v: v.transform(transform),
+                    },
+                    AreaShape::Disk {
+                        center,
+                        normal,
+                        radius,
+                    } => {
+                        let (tangent, _) = sampling::orthonormal_basis(&normal);
+                        AreaShape::Disk {
+                            center: center.transform(transform),
+                            normal: normal.transform(transform).normalize(),
+                            radius: radius * tangent.transform(transform).magnitude(),
+                        }
+                    }
+                    AreaShape::Sphere { center, radius } => AreaShape::Sphere {
+                        center: center.transform(transform),
+                        radius: radius * Vector3::I.transform(transform).magnitude(),
+                    },
+                };
+                Self::Area(AreaLight { shape, ..x })
+            }
+            Self::Mesh(x) => {
+                let triangles = x
+                    .triangles()
+                    .iter()
+                    .map(|t| t.map(|p| p.transform(transform)))
+                    .collect();
+                Self::Mesh(MeshLight::new(triangles, x.radiance).with_samples(x.samples))
+            }
+        }
+    }
+}
+
+impl Illuminate for Light {
+    fn illuminate(&self, p: &Point3) -> LightSample {
+        match self {
+            Self::Point(x) => x.illuminate(p),
+            Self::Directional(x) => x.illuminate(p),
+            Self::Spot(x) => x.illuminate(p),
+            Self::Area(x) => x.illuminate(p),
+            Self::Mesh(x) => x.illuminate(p),
+        }
+    }
+
+    fn samples(&self, p: &Point3) -> Vec<LightSample> {
+        match self {
+            Self::Area(x) => x.samples(p),
+            Self::Mesh(x) => x.samples(p),
+            _ => vec![self.illuminate(p)],
+        }
+    }
+}
+
+impl From<PointLight> for Light {
+    fn from(l: PointLight) -> Self {
+        Light::Point(l)
+    }
+}
+
+impl From<DirectionalLight> for Light {
+    fn from(l: DirectionalLight) -> Self {
+        Light::Directional(l)
+    }
+}
+
+impl From<SpotLight> for Light {
+    fn from(l: SpotLight) -> Self {
+        Light::Spot(l)
+    }
+}
+
+impl From<AreaLight> for Light {
+    fn from(l: AreaLight) -> Self {
+        Light::Area(l)
+    }
+}
+
+impl From<MeshLight> for Light {
+    fn from(l: MeshLight) -> Self {
+        Light::Mesh(l)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    use crate::algebra::EPSILON;
+    use crate::light::{AreaShape, MeshLight};
+
+    #[test]
+    fn point_light_falls_off() {
+        let light = Light::from(PointLight::new(
+            Point3::new(0.0, 2.0, 0.0),
+            Color::WHITE,
+            Color::WHITE,
+            Color::WHITE,
+            8.0,
+        ));
+        let sample = light.illuminate(&Point3::O);
+
+        assert_eq!(sample.direction, Vector3::J);
+        assert_eq!(sample.distance, 2.0);
+        assert_eq!(sample.intensity, 2.0);
+    }
+
+    #[test]
+    fn directional_light_is_constant() {
+        let light = Light::from(DirectionalLight::new(
+            Vector3::new(0.0, -2.0, 0.0),
+            Color::WHITE,
+            Color::WHITE,
+            Color::WHITE,
+            0.5,
+        ));
+
+        for p in [Point3::O, Point3::new(1e6, -1e6, 3.0)] {
+            let sample = light.illuminate(&p);
+            assert_eq!(sample.direction, Vector3::J);
+            assert_eq!(sample.distance, f64::INFINITY);
+            assert_eq!(sample.intensity, 0.5);
+        }
+    }
+
+    #[test]
+    fn spot_light_cone() {
+        let light = Light::from(SpotLight::new(
+            Point3::new(0.0, 2.0, 0.0),
+            -Vector3::J,
+            30.0_f64.to_radians(),
+            45.0_f64.to_radians(),
+            Color::WHITE,
+            Color::WHITE,
+            Color::WHITE,
+            8.0,
+        ));
+
+        let center = light.illuminate(&Point3::O);
+        assert_eq!(center.direction, Vector3::J);
+        assert_eq!(center.intensity, 2.0);
+
+        // 40 degrees off the axis, between the inner and outer cone
+        let edge = light.illuminate(&Point3::new(2.0 * 40.0_f64.to_radians().tan(), 0.0, 0.0));
+        assert!(
+            edge.intensity > 0.0 && edge.intensity < 2.0,
+            "{}",
+            edge.intensity
+        );
+
+        let outside = light.illuminate(&Point3::new(2.0 * 50.0_f64.to_radians().tan(), 0.0, 0.0));
+        assert_eq!(outside.intensity, 0.0);
+        assert_eq!(light.illuminate(&Point3::new(0.0, 3.0, 0.0)).intensity, 0.0);
+    }
+
+    #[test]
+    fn area_lights_match_point_light_from_afar() {
+        let center = Point3::new(0.0, 100.0, 0.0);
+        let shapes = [
+            AreaShape::Rectangle {
+                center,
+                u: Vector3::I,
+                v: Vector3::K,
+            },
+            AreaShape::Disk {
+                center,
+                normal: -Vector3::J,
+                radius: 1.0,
+            },
+            AreaShape::Sphere {
+                center,
+                radius: 1.0,
+            },
+        ];
+
+        for shape in shapes {
+            // the cosine weighted samples on the sphere need more strata to converge as tightly
+            let count = match shape {
+                AreaShape::Sphere { .. } => 1024,
+                _ => 64,
+            };
+            let light = Light::from(
+                AreaLight::new(shape, Color::WHITE, Color::WHITE, Color::WHITE, 1e4)
+                    .with_samples(count),
+            );
+            let samples = light.samples(&Point3::O);
+            assert_eq!(samples.len(), count as usize);
+
+            let intensity: f64 = samples.iter().map(|s| s.intensity).sum();
+            assert!((intensity - 1.0).abs() < 0.01, "{:?} {}", shape, intensity);
+            assert!(samples.iter().all(|s| s.direction.dot(&Vector3::J) > 0.99));
+        }
+    }
+
+    #[test]
+    fn sphere_light_from_its_center() {
+        let light = AreaLight::new(
+            AreaShape::Sphere {
+                center: Point3::O,
+                radius: 1.0,
+            },
+            Color::WHITE,
+            Color::WHITE,
+            Color::WHITE,
+            1.0,
+        );
+
+        // the surface faces away from the center, which is in the dark
+        for s in light.samples(&Point3::O) {
+            assert!((s.direction.magnitude() - 1.0).abs() < 1e-9, "{:?}", s);
+            assert_eq!(s.intensity, 0.0);
+        }
+    }
+
+    #[test]
+    fn one_sided_area_light() {
+        let light = AreaLight::new(
+            AreaShape::Rectangle {
+                center: Point3::O,
+                u: Vector3::I,
+                v: Vector3::K,
+            },
+            Color::WHITE,
+            Color::WHITE,
+            Color::WHITE,
+            1.0,
+        );
+
+        // u x v points down, so nothing above the rectangle is lit
+        assert!(light
+            .samples(&Point3::new(0.0, 1.0, 0.0))
+            .iter()
+            .all(|s| s.intensity == 0.0));
+        assert!(light.illuminate(&Point3::new(0.0, -1.0, 0.0)).intensity > 0.0);
+    }
+
+    #[test]
+    fn mesh_light_samples_by_area() {
+        // a 2x2 square split into triangles of different sizes, facing the origin from afar
+        let (a, b, c, d) = (
+            Point3::new(-1.0, 100.0, -1.0),
+            Point3::new(1.0, 100.0, -1.0),
+            Point3::new(1.0, 100.0, 1.0),
+            Point3::new(-1.0, 100.0, 1.0),
+        );
+        let e = Point3::new(-1.0, 100.0, -0.5);
+        let light =
+            MeshLight::new(vec![[a, b, e], [e, b, c], [e, c, d]], Color::WHITE).with_samples(100);
+        assert!((light.area() - 4.0).abs() < 1e-9);
+
+        let samples = light.samples(&Point3::O);
+        let intensity: f64 = samples.iter().map(|s| s.intensity).sum();
+        assert!((intensity - 4.0 / PI * 1e-4).abs() < 1e-7, "{}", intensity);
+
+        for s in &samples {
+            let p = Point3::O + (s.distance + EPSILON) * s.direction;
+            assert!(
+                p.x.abs() <= 1.0 + 1e-6 && p.z.abs() <= 1.0 + 1e-6,
+                "{:?}",
+                p
+            );
+        }
+    }
+}
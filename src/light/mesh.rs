@@ -0,0 +1,121 @@
+use std::f64::consts::PI;
+
+use rand::random;
+
+use crate::algebra::{Point2, Point3, Vector3, EPSILON};
+use crate::color::Color;
+use crate::sampling;
+
+use super::{Illuminate, LightSample};
+
+/// Light emitted by a set of triangles in world space, like the surface of an emissive mesh.
+///
+/// Every point of the surface emits `radiance` on both sides, and points are sampled
+/// uniformly by area, so larger triangles receive proportionally more samples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshLight {
+    pub radiance: Color,
+    /// Number of samples taken across the surface at every shading point.
+    pub samples: u32,
+    triangles: Vec<[Point3; 3]>,
+    // running sum of the areas of the triangles, normalized to end at 1
+    cdf: Vec<f64>,
+    area: f64,
+}
+
+impl MeshLight {
+    pub fn new(triangles: Vec<[Point3; 3]>, radiance: Color) -> Self {
+        let mut area = 0.0;
+        let mut cdf = triangles
+            .iter()
+            .map(|[a, b, c]| {
+                area += 0.5 * (*b - *a).cross(&(*c - *a)).magnitude();
+                area
+            })
+            .collect::<Vec<f64>>();
+        if area > 0.0 {
+            cdf.iter_mut().for_each(|x| *x /= area);
+        }
+
+        Self {
+            radiance,
+            samples: 16,
+            triangles,
+            cdf,
+            area,
+        }
+    }
+
+    pub fn with_samples(mut self, samples: u32) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    /// Total area of the surface.
+    pub fn area(&self) -> f64 {
+        self.area
+    }
+
+    pub fn triangles(&self) -> &[[Point3; 3]] {
+        &self.triangles
+    }
+
+    fn sample_at(&self, p: &Point3, uv: &Point2) -> LightSample {
+        if self.area == 0.0 {
+            return LightSample {
+                direction: Vector3::J,
+                distance: 0.0,
+                intensity: 0.0,
+            };
+        }
+
+        // pick a triangle with the first coordinate, then reuse what is left of it to pick
+        // a point inside the triangle so the samples stay stratified
+        let i = self
+            .cdf
+            .partition_point(|&x| x < uv.x)
+            .min(self.triangles.len() - 1);
+        let start = if i == 0 { 0.0 } else { self.cdf[i - 1] };
+        let u = ((uv.x - start) / (self.cdf[i] - start)).clamp(0.0, 1.0);
+
+        let [a, b, c] = self.triangles[i];
+        let s = u.sqrt();
+        let point = a + s * (1.0 - uv.y) * (b - a) + s * uv.y * (c - a);
+        let normal = (b - a).cross(&(c - a)).normalize();
+
+        let to_light = point - *p;
+        let distance = to_light.magnitude();
+        let direction = to_light / distance;
+        let cos_light = direction.dot(&normal).abs();
+
+        // a Lambertian emitter of area A lights like a point light of intensity A / PI along
+        // its normal, in the convention of the other lights
+        LightSample {
+            direction,
+            // stop shadow rays just short of the surface they are aimed at
+            distance: distance - EPSILON,
+            intensity: self.area / PI * cos_light / (distance * distance),
+        }
+    }
+}
+
+impl Illuminate for MeshLight {
+    /// Returns a single sample at a random position on the surface.
+    fn illuminate(&self, p: &Point3) -> LightSample {
+        self.sample_at(p, &Point2::new(random(), random()))
+    }
+
+    fn samples(&self, p: &Point3) -> Vec<LightSample> {
+        let n = self.samples as f64;
+        sampling::stratified_2d(self.samples)
+            .iter()
+            .map(|uv| {
+                let sample = self.sample_at(p, uv);
+                LightSample {
+                    intensity: sample.intensity / n,
+                    ..sample
+                }
+            })
+            .collect()
+    }
+}
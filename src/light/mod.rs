@@ -0,0 +1,17 @@
+pub use area::AreaLight;
+pub use area::AreaShape;
+pub use directional::DirectionalLight;
+pub use light::Illuminate;
+pub use light::Light;
+pub use light::LightSample;
+pub use mesh::MeshLight;
+pub use point::PointLight;
+pub use spot::SpotLight;
+
+mod area;
+mod directional;
+#[allow(clippy::module_inception)]
+mod light;
+mod mesh;
+mod point;
+mod spot;
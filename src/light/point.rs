@@ -1,6 +1,8 @@
 use crate::algebra::Point3;
 use crate::color::Color;
 
+use super::{Illuminate, LightSample};
+
 #[derive(Default, Debug, Clone, Copy, PartialEq)]
 pub struct PointLight {
     pub position: Point3,
@@ -33,3 +35,16 @@ impl PointLight {
         self.intensity / r2
     }
 }
+
+impl Illuminate for PointLight {
+    fn illuminate(&self, p: &Point3) -> LightSample {
+        let to_light = self.position - *p;
+        let distance = to_light.magnitude();
+
+        LightSample {
+            direction: to_light / distance,
+            distance,
+            intensity: self.intensity_at(p),
+        }
+    }
+}
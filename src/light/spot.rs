@@ -0,0 +1,77 @@
+use crate::algebra::{Point3, Vector3};
+use crate::color::Color;
+
+use super::{Illuminate, LightSample};
+
+/// Point light emitting only inside a cone around `direction`.
+///
+/// The light is at full strength within `inner_angle` of the axis and fades out smoothly
+/// between `inner_angle` and `outer_angle`, where it reaches zero. Both angles are measured from
+/// the axis, in radians.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct SpotLight {
+    pub position: Point3,
+    /// Direction the axis of the cone points in.
+    pub direction: Vector3,
+    pub inner_angle: f64,
+    pub outer_angle: f64,
+    pub ambient: Color,
+    pub diffuse: Color,
+    pub specular: Color,
+    pub intensity: f64,
+}
+
+impl SpotLight {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        position: Point3,
+        direction: Vector3,
+        inner_angle: f64,
+        outer_angle: f64,
+        ambient: Color,
+        diffuse: Color,
+        specular: Color,
+        intensity: f64,
+    ) -> Self {
+        Self {
+            position,
+            direction: direction.normalize(),
+            inner_angle,
+            outer_angle: outer_angle.max(inner_angle),
+            ambient,
+            diffuse,
+            specular,
+            intensity,
+        }
+    }
+
+    /// Returns the attenuation of the cone for a unit direction leaving the light, 1 inside the
+    /// inner cone and 0 outside the outer one.
+    pub fn falloff(&self, dir: &Vector3) -> f64 {
+        let cos_a = dir.dot(&self.direction);
+        let (cos_inner, cos_outer) = (self.inner_angle.cos(), self.outer_angle.cos());
+        if cos_a >= cos_inner {
+            return 1.0;
+        }
+        if cos_a <= cos_outer {
+            return 0.0;
+        }
+
+        let t = (cos_a - cos_outer) / (cos_inner - cos_outer);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Illuminate for SpotLight {
+    fn illuminate(&self, p: &Point3) -> LightSample {
+        let to_light = self.position - *p;
+        let distance = to_light.magnitude();
+        let direction = to_light / distance;
+
+        LightSample {
+            direction,
+            distance,
+            intensity: self.intensity * self.falloff(&-direction) / (distance * distance),
+        }
+    }
+}
@@ -7,6 +7,14 @@ pub struct Phong {
     pub specular: Texture,
     pub shininess: f64,
     pub reflectance: f64,
+    /// Fraction of the light passing through the surface, split between reflection and
+    /// refraction by the Fresnel equations.
+    pub transparency: f64,
+    /// Refractive index of the medium on the inside of the surface, the side the normal points
+    /// away from.
+    pub refractive_index: f64,
+    /// Radiance emitted by the surface on both of its sides, unaffected by any light.
+    pub emission: Color,
 }
 
 impl Phong {
@@ -22,6 +30,9 @@ impl Phong {
             specular: specular.into(),
             shininess,
             reflectance: 0.0,
+            transparency: 0.0,
+            refractive_index: 1.0,
+            emission: Color::BLACK,
         }
     }
 
@@ -33,6 +44,26 @@ impl Phong {
         self.reflectance = reflectance;
         self
     }
+
+    pub fn with_transparency(mut self, transparency: f64) -> Self {
+        self.transparency = transparency;
+        self
+    }
+
+    pub fn with_refractive_index(mut self, refractive_index: f64) -> Self {
+        self.refractive_index = refractive_index;
+        self
+    }
+
+    /// Makes the surface glow with the given color scaled by `strength`.
+    pub fn with_emission(mut self, color: Color, strength: f64) -> Self {
+        self.emission = color * strength;
+        self
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        self.emission != Color::BLACK
+    }
 }
 
 impl Default for Phong {
@@ -40,3 +71,36 @@ impl Default for Phong {
         Self::new(Color::WHITE * 0.03, Color::BLUE, Color::WHITE, 20.0)
     }
 }
+
+/// Fraction of unpolarized light reflected at a dielectric interface, going from a medium with
+/// refractive index `n1` into one with `n2`, for the cosine `cos_i` of the incident angle.
+pub fn fresnel(cos_i: f64, n1: f64, n2: f64) -> f64 {
+    let sin2_t = (n1 / n2).powi(2) * (1.0 - cos_i * cos_i);
+    if sin2_t >= 1.0 {
+        // total internal reflection
+        return 1.0;
+    }
+
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let rs = (n1 * cos_i - n2 * cos_t) / (n1 * cos_i + n2 * cos_t);
+    let rp = (n2 * cos_i - n1 * cos_t) / (n2 * cos_i + n1 * cos_t);
+    (rs * rs + rp * rp) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fresnel_reflectance() {
+        // about 4% at normal incidence on glass, from either side
+        assert!((fresnel(1.0, 1.0, 1.5) - 0.04).abs() < 1e-12);
+        assert!((fresnel(1.0, 1.5, 1.0) - 0.04).abs() < 1e-12);
+
+        // everything is reflected at grazing angles and past the critical angle
+        assert!(fresnel(1e-6, 1.0, 1.5) > 0.99);
+        assert_eq!(fresnel(0.5, 1.5, 1.0), 1.0);
+
+        assert!(fresnel(0.5, 1.0, 1.5) > fresnel(0.9, 1.0, 1.5));
+    }
+}
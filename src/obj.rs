@@ -0,0 +1,547 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::{
+    algebra::{Point2, Point3, Vector3},
+    color::Color,
+    entity::Entity,
+    geometry::{Face, Mesh, Vertex},
+    material::Phong,
+    texture::{Image, Texture},
+};
+
+/// Faces sharing a group name and material, as read from a Wavefront OBJ file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjGroup {
+    pub name: String,
+    pub material: Option<String>,
+    pub mesh: Mesh,
+}
+
+/// Meshes and materials read from a Wavefront OBJ file and the MTL libraries it references.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Obj {
+    pub groups: Vec<ObjGroup>,
+    pub materials: HashMap<String, Phong>,
+}
+
+impl Obj {
+    pub fn open(path: &str) -> Result<Self, String> {
+        parse(path, true)
+    }
+
+    /// Turns every group into an entity, with its material from the MTL library if there is
+    /// one.
+    pub fn into_entities(self) -> Vec<Entity> {
+        let materials = self.materials;
+
+        self.groups
+            .into_iter()
+            .map(|group| {
+                let material = group
+                    .material
+                    .and_then(|name| materials.get(&name).cloned())
+                    .unwrap_or_default();
+
+                Entity::from(group.mesh).with_material(material)
+            })
+            .collect()
+    }
+}
+
+impl Mesh {
+    /// Reads every face of an OBJ file into a single mesh, ignoring groups and materials.
+    pub fn from_obj_file(path: &str) -> Result<Self, String> {
+        let obj = parse(path, false)?;
+        let faces = obj
+            .groups
+            .into_iter()
+            .flat_map(|group| group.mesh.faces)
+            .collect::<Vec<Face>>();
+
+        Ok(Self::from(faces))
+    }
+}
+
+// a face corner, as indices into the positions, texture coordinates and normals
+type Corner = (usize, Option<usize>, Option<usize>);
+
+struct RawGroup {
+    name: String,
+    material: Option<String>,
+    faces: Vec<Vec<Corner>>,
+}
+
+fn parse(path: &str, load_materials: bool) -> Result<Obj, String> {
+    let source = fs::read_to_string(path).map_err(|_| format!("Cannot read file {}", path))?;
+    let dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut materials = HashMap::new();
+
+    let mut groups = Vec::new();
+    let mut current = RawGroup {
+        name: "default".to_string(),
+        material: None,
+        faces: Vec::new(),
+    };
+
+    for (n, line) in source.lines().enumerate() {
+        let error = |msg: &str| format!("{}:{}: {}", path, n + 1, msg);
+
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args = tokens.collect::<Vec<&str>>();
+
+        match keyword {
+            "v" => {
+                let [x, y, z] = parse_floats(&args).ok_or_else(|| error("Invalid vertex"))?;
+                positions.push(Point3::new(x, y, z));
+            }
+            "vt" => {
+                let u = args.first().and_then(|x| x.parse::<f64>().ok());
+                let v = args.get(1).map_or(Some(0.0), |x| x.parse::<f64>().ok());
+                match (u, v) {
+                    (Some(u), Some(v)) => uvs.push(Point2::new(u, v)),
+                    _ => return Err(error("Invalid texture coordinate")),
+                }
+            }
+            "vn" => {
+                let [x, y, z] = parse_floats(&args).ok_or_else(|| error("Invalid normal"))?;
+                normals.push(Vector3::new(x, y, z));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(error("Face with less than 3 vertices"));
+                }
+
+                let corners = args
+                    .iter()
+                    .map(|arg| parse_corner(arg, positions.len(), uvs.len(), normals.len()))
+                    .collect::<Option<Vec<Corner>>>()
+                    .ok_or_else(|| error("Invalid face"))?;
+                current.faces.push(corners);
+            }
+            "g" | "o" | "usemtl" => {
+                let mut next = RawGroup {
+                    name: current.name.clone(),
+                    material: current.material.clone(),
+                    faces: Vec::new(),
+                };
+
+                if keyword == "usemtl" {
+                    next.material = Some(args.join(" "));
+                } else {
+                    next.name = args.join(" ");
+                }
+
+                let previous = std::mem::replace(&mut current, next);
+                if !previous.faces.is_empty() {
+                    groups.push(previous);
+                }
+            }
+            "mtllib" if load_materials => {
+                for file in args {
+                    let mtl_path = dir.join(file);
+                    materials.extend(parse_mtl(&mtl_path)?);
+                }
+            }
+            // smoothing groups, lines, points, and anything else we don't render
+            _ => {}
+        }
+    }
+
+    if !current.faces.is_empty() {
+        groups.push(current);
+    }
+
+    // vertices without a normal get the area weighted average of the faces around their
+    // position, shared across groups so that there are no seams between them
+    let needs_normals = groups
+        .iter()
+        .flat_map(|g| g.faces.iter().flatten())
+        .any(|corner| corner.2.is_none());
+    let mut smooth_normals = vec![Vector3::ZERO; positions.len()];
+    if needs_normals {
+        for face in groups.iter().flat_map(|g| g.faces.iter()) {
+            let points = face
+                .iter()
+                .rev()
+                .map(|c| positions[c.0])
+                .collect::<Vec<_>>();
+            let n = Face::area_weighted_normal(&points);
+            for corner in face {
+                smooth_normals[corner.0] = smooth_normals[corner.0] + n;
+            }
+        }
+    }
+
+    let groups = groups
+        .into_iter()
+        .map(|group| {
+            let mut indices = HashMap::new();
+            let mut vertices = Vec::new();
+            let mut faces = Vec::new();
+
+            for face in &group.faces {
+                let points = face.iter().map(|c| positions[c.0]).collect::<Vec<_>>();
+                if Face::area_weighted_normal(&points).magnitude() == 0.0 {
+                    // skip degenerate faces, they can't be hit and have no normal
+                    continue;
+                }
+
+                // reversed so that the face normal agrees with the counter clockwise winding
+                let face = face
+                    .iter()
+                    .rev()
+                    .map(|corner| {
+                        *indices.entry(*corner).or_insert_with(|| {
+                            let (p, uv, n) = *corner;
+                            let normal = match n {
+                                Some(n) => normals[n],
+                                None => smooth_normals[p],
+                            };
+                            let normal = if normal.magnitude() > 0.0 {
+                                normal.normalize()
+                            } else {
+                                normal
+                            };
+
+                            let vertex = Vertex::from(positions[p]).with_normal(normal);
+                            vertices.push(match uv {
+                                Some(uv) => vertex.with_uv(uvs[uv]),
+                                None => vertex,
+                            });
+                            vertices.len() - 1
+                        })
+                    })
+                    .collect::<Vec<usize>>();
+                faces.push(face);
+            }
+
+            Ok(ObjGroup {
+                name: group.name,
+                material: group.material,
+                mesh: Mesh::from_indexed(vertices, &faces)?,
+            })
+        })
+        .collect::<Result<Vec<ObjGroup>, String>>()?;
+
+    Ok(Obj { groups, materials })
+}
+
+fn parse_floats(args: &[&str]) -> Option<[f64; 3]> {
+    if args.len() < 3 {
+        return None;
+    }
+
+    Some([
+        args[0].parse().ok()?,
+        args[1].parse().ok()?,
+        args[2].parse().ok()?,
+    ])
+}
+
+/// Parses a `v`, `v/vt`, `v//vn` or `v/vt/vn` face corner. Indices are 1 based, negative ones
+/// count back from the most recently defined element.
+fn parse_corner(
+    arg: &str,
+    num_positions: usize,
+    num_uvs: usize,
+    num_normals: usize,
+) -> Option<Corner> {
+    let mut parts = arg.split('/');
+
+    let p = parse_index(parts.next()?, num_positions)?;
+    let uv = match parts.next() {
+        Some(x) if !x.is_empty() => Some(parse_index(x, num_uvs)?),
+        _ => None,
+    };
+    let n = match parts.next() {
+        Some(x) if !x.is_empty() => Some(parse_index(x, num_normals)?),
+        _ => None,
+    };
+
+    Some((p, uv, n))
+}
+
+fn parse_index(arg: &str, count: usize) -> Option<usize> {
+    let i = arg.parse::<i64>().ok()?;
+    let count = count as i64;
+
+    if i > 0 && i <= count {
+        Some((i - 1) as usize)
+    } else if i < 0 && -i <= count {
+        Some((count + i) as usize)
+    } else {
+        None
+    }
+}
+
+struct MtlMaterial {
+    ambient: Color,
+    diffuse: Color,
+    specular: Color,
+    shininess: f64,
+    transparency: f64,
+    refractive_index: f64,
+    emission: Color,
+    diffuse_map: Option<Image>,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        Self {
+            ambient: Color::WHITE * 0.03,
+            diffuse: Color::WHITE * 0.8,
+            specular: Color::WHITE,
+            shininess: 20.0,
+            transparency: 0.0,
+            refractive_index: 1.0,
+            emission: Color::BLACK,
+            diffuse_map: None,
+        }
+    }
+}
+
+impl From<MtlMaterial> for Phong {
+    fn from(m: MtlMaterial) -> Self {
+        let diffuse = match m.diffuse_map {
+            Some(image) => Texture::from(image),
+            None => Texture::from(m.diffuse),
+        };
+
+        Phong::new(m.ambient, diffuse, m.specular, m.shininess)
+            .with_transparency(m.transparency)
+            .with_refractive_index(m.refractive_index)
+            .with_emission(m.emission, 1.0)
+    }
+}
+
+/// Reads the materials of an MTL library, mapping `Ka`, `Kd`, `Ks`, `Ke`, `Ns`, `d`, `Tr`, `Ni`
+/// and `map_Kd` onto Phong materials. Texture paths are relative to the library.
+fn parse_mtl(path: &Path) -> Result<HashMap<String, Phong>, String> {
+    let display = path.display();
+    let source = fs::read_to_string(path).map_err(|_| format!("Cannot read file {}", display))?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+
+    for (n, line) in source.lines().enumerate() {
+        let error = |msg: &str| format!("{}:{}: {}", display, n + 1, msg);
+
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args = tokens.collect::<Vec<&str>>();
+
+        if keyword == "newmtl" {
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material.into());
+            }
+            current = Some((args.join(" "), MtlMaterial::default()));
+            continue;
+        }
+
+        let material = match current.as_mut() {
+            Some((_, material)) => material,
+            None => continue,
+        };
+
+        match keyword {
+            "Ka" | "Kd" | "Ks" | "Ke" => {
+                let [r, g, b] = parse_floats(&args).ok_or_else(|| error("Invalid color"))?;
+                let color = Color::new(r, g, b);
+                match keyword {
+                    "Ka" => material.ambient = color,
+                    "Kd" => material.diffuse = color,
+                    "Ks" => material.specular = color,
+                    _ => material.emission = color,
+                }
+            }
+            "Ns" | "d" | "Tr" | "Ni" => {
+                let x: f64 = args
+                    .first()
+                    .and_then(|x| x.parse().ok())
+                    .ok_or_else(|| error(&format!("Invalid value for {}", keyword)))?;
+                match keyword {
+                    "Ns" => material.shininess = x,
+                    "d" => material.transparency = 1.0 - x,
+                    "Tr" => material.transparency = x,
+                    _ => material.refractive_index = x,
+                }
+            }
+            "map_Kd" => {
+                let file = texture_file(&args).ok_or_else(|| error("Missing texture file"))?;
+                let image = Image::open(dir.join(&file))
+                    .map_err(|_| error(&format!("Cannot read texture {}", file)))?;
+                material.diffuse_map = Some(image);
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((name, material)) = current {
+        materials.insert(name, material.into());
+    }
+
+    Ok(materials)
+}
+
+/// Returns the file name of a texture statement, skipping the options before it. The name
+/// may contain spaces.
+fn texture_file(args: &[&str]) -> Option<String> {
+    let mut i = 0;
+    while let Some(option) = args.get(i).filter(|a| a.starts_with('-')) {
+        i += 1;
+        match *option {
+            "-mm" => i += 2,
+            // one to three coordinates
+            "-o" | "-s" | "-t" => {
+                let coordinates = args[i..]
+                    .iter()
+                    .take(3)
+                    .take_while(|a| a.parse::<f64>().is_ok())
+                    .count();
+                i += coordinates.max(1);
+            }
+            _ => i += 1,
+        }
+    }
+
+    args.get(i..)
+        .filter(|file| !file.is_empty())
+        .map(|file| file.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::{Bounded, Intersect};
+
+    use super::*;
+
+    fn write_obj(name: &str) -> String {
+        let dir = std::env::temp_dir();
+        fs::write(
+            dir.join(format!("{}.mtl", name)),
+            "newmtl red\n\
+             Ka 0.1 0.1 0.1\n\
+             Kd 1 0 0\n\
+             Ks 0.5 0.5 0.5\n\
+             Ns 50\n\
+             d 0.25\n\
+             Ni 1.33\n\
+             newmtl green\n\
+             Kd 0 1 0\n\
+             Ke 0 2 0\n",
+        )
+        .unwrap();
+
+        let path = dir.join(format!("{}.obj", name));
+        fs::write(
+            &path,
+            format!(
+                "mtllib {}.mtl\n\
+                 v 0 0 0\n\
+                 v 1 0 0\n\
+                 v 1 1 0\n\
+                 v 0 1 0\n\
+                 vt 0 0\n\
+                 vt 1 0\n\
+                 vt 1 1\n\
+                 vt 0 1\n\
+                 vn 0 0 1\n\
+                 g floor\n\
+                 usemtl red\n\
+                 f 1/1/1 2/2/1 3/3/1 4/4/1\n\
+                 g wall\n\
+                 usemtl green\n\
+                 v 0 0 1\n\
+                 v 0 1 1\n\
+                 f -6//1 -5//1 -1//1 # negative indices\n\
+                 f -6 -1 -2\n",
+                name
+            ),
+        )
+        .unwrap();
+
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn open() {
+        let obj = Obj::open(&write_obj("raytox_open")).unwrap();
+
+        assert_eq!(obj.groups.len(), 2);
+        assert_eq!(obj.groups[0].name, "floor");
+        assert_eq!(obj.groups[0].material, Some("red".to_string()));
+        assert_eq!(obj.groups[0].mesh.faces.len(), 1);
+        assert_eq!(obj.groups[0].mesh.faces[0].vertices.len(), 4);
+        assert_eq!(obj.groups[0].mesh.faces[0].normal, Vector3::K);
+        assert_eq!(obj.groups[1].name, "wall");
+        assert_eq!(obj.groups[1].mesh.faces.len(), 2);
+
+        let red = &obj.materials["red"];
+        assert_eq!(red.diffuse, Texture::from(Color::RED));
+        assert_eq!(red.specular, Texture::from(Color::WHITE * 0.5));
+        assert_eq!(red.shininess, 50.0);
+        assert_eq!(red.transparency, 0.75);
+        assert_eq!(red.refractive_index, 1.33);
+        assert_eq!(obj.materials["green"].transparency, 0.0);
+        assert_eq!(obj.materials["green"].emission, Color::GREEN * 2.0);
+        assert_eq!(red.emission, Color::BLACK);
+    }
+
+    #[test]
+    fn texture_coordinates() {
+        let obj = Obj::open(&write_obj("raytox_uv")).unwrap();
+        let floor = &obj.groups[0].mesh;
+
+        let ray = crate::algebra::Ray::new(Point3::new(0.25, 0.5, 1.0), -Vector3::K);
+        let uv = floor.intersect(&ray).unwrap().uv.unwrap();
+        assert!((uv.x - 0.25).abs() < 1e-9 && (uv.y - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn from_obj_file() {
+        let mesh = Mesh::from_obj_file(&write_obj("raytox_mesh")).unwrap();
+        let bounds = mesh.bounding_box().unwrap();
+
+        assert_eq!(mesh.faces.len(), 3);
+        assert_eq!(bounds.min, Point3::O);
+        assert_eq!(bounds.max, Point3::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn texture_options() {
+        assert_eq!(
+            texture_file(&["-bm", "0.5", "-s", "2", "2", "-clamp", "on", "my", "wood.png"]),
+            Some("my wood.png".to_string())
+        );
+        assert_eq!(
+            texture_file(&["-o", "0.5", "-mm", "0", "1", "wood.png"]),
+            Some("wood.png".to_string())
+        );
+        assert_eq!(texture_file(&["-bm", "1.0"]), None);
+    }
+
+    #[test]
+    fn invalid_index() {
+        let path = std::env::temp_dir().join("raytox_invalid.obj");
+        fs::write(&path, "v 0 0 0\nv 1 0 0\nf 1 2 3\n").unwrap();
+
+        let error = Mesh::from_obj_file(path.to_str().unwrap()).unwrap_err();
+        assert!(error.ends_with(":3: Invalid face"));
+    }
+}
@@ -0,0 +1,181 @@
+use std::f64::consts::PI;
+
+use rand::random;
+
+use crate::algebra::{Point2, Vector3};
+
+/// Returns `n` jittered sample positions in the unit square.
+///
+/// The largest square grid that fits in `n` is stratified, with one random sample per cell,
+/// and any remaining samples are placed uniformly at random. A single sample sits in the center
+/// of the square, so rendering with one sample per pixel stays deterministic.
+pub fn stratified_2d(n: u32) -> Vec<Point2> {
+    if n == 1 {
+        return vec![Point2::new(0.5, 0.5)];
+    }
+
+    let k = (n as f64).sqrt() as u32;
+    let cell = 1.0 / k as f64;
+
+    let mut samples = Vec::with_capacity(n as usize);
+    for j in 0..k {
+        for i in 0..k {
+            samples.push(Point2::new(
+                (i as f64 + random::<f64>()) * cell,
+                (j as f64 + random::<f64>()) * cell,
+            ));
+        }
+    }
+    for _ in k * k..n {
+        samples.push(Point2::new(random(), random()));
+    }
+
+    samples
+}
+
+/// Returns `n` jittered samples between 0 and 1, one in each of `n` equal intervals. Like
+/// [`stratified_2d`], a single sample sits in the middle.
+pub fn stratified_1d(n: u32) -> Vec<f64> {
+    if n == 1 {
+        return vec![0.5];
+    }
+
+    (0..n)
+        .map(|i| (i as f64 + random::<f64>()) / n as f64)
+        .collect()
+}
+
+/// Maps a position in the unit square onto the unit disk, preserving areas and keeping
+/// stratified samples well spread, with the center of the square at the center of the disk.
+pub fn concentric_disk(p: &Point2) -> Point2 {
+    let (a, b) = (2.0 * p.x - 1.0, 2.0 * p.y - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return Point2::new(0.0, 0.0);
+    }
+
+    let (r, phi) = if a.abs() > b.abs() {
+        (a, PI / 4.0 * (b / a))
+    } else {
+        (b, PI / 2.0 - PI / 4.0 * (a / b))
+    };
+    Point2::new(r * phi.cos(), r * phi.sin())
+}
+
+/// Returns two unit vectors that form an orthonormal basis together with the unit vector `n`.
+pub fn orthonormal_basis(n: &Vector3) -> (Vector3, Vector3) {
+    let helper = if n.x.abs() > 0.9 {
+        Vector3::J
+    } else {
+        Vector3::I
+    };
+    let u = helper.cross(n).normalize();
+    let v = n.cross(&u);
+    (u, v)
+}
+
+/// Returns a random direction around the unit vector `axis` whose angle `a` to the axis is
+/// distributed proportionally to `cos(a)^exponent`.
+///
+/// An exponent of 1 gives a cosine weighted hemisphere, with a pdf of `cos(a) / PI`. In
+/// general the pdf is `(exponent + 1) / (2 * PI) * cos(a)^exponent`.
+pub fn cosine_power_direction(axis: &Vector3, exponent: f64) -> Vector3 {
+    let (u, v) = orthonormal_basis(axis);
+    let phi = 2.0 * PI * random::<f64>();
+    let cos_theta = random::<f64>().powf(1.0 / (exponent + 1.0));
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+
+    sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + cos_theta * *axis
+}
+
+/// Density of [`cosine_power_direction`] for a direction at cosine `cos_a` to the axis.
+pub fn cosine_power_pdf(cos_a: f64, exponent: f64) -> f64 {
+    if cos_a <= 0.0 {
+        return 0.0;
+    }
+    (exponent + 1.0) / (2.0 * PI) * cos_a.powf(exponent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stratified() {
+        assert_eq!(stratified_2d(1), vec![Point2::new(0.5, 0.5)]);
+        assert!(stratified_2d(0).is_empty());
+
+        let samples = stratified_2d(16);
+        assert_eq!(samples.len(), 16);
+        for (n, p) in samples.iter().enumerate() {
+            let (i, j) = (n % 4, n / 4);
+            assert_eq!(((p.x * 4.0) as usize, (p.y * 4.0) as usize), (i, j));
+        }
+
+        let samples = stratified_2d(6);
+        assert_eq!(samples.len(), 6);
+        assert!(samples
+            .iter()
+            .all(|p| (0.0..1.0).contains(&p.x) && (0.0..1.0).contains(&p.y)));
+    }
+
+    #[test]
+    fn stratified_times() {
+        assert_eq!(stratified_1d(1), vec![0.5]);
+
+        let samples = stratified_1d(8);
+        for (i, t) in samples.iter().enumerate() {
+            assert!((i as f64 / 8.0..(i + 1) as f64 / 8.0).contains(t));
+        }
+    }
+
+    #[test]
+    fn concentric() {
+        assert_eq!(
+            concentric_disk(&Point2::new(0.5, 0.5)),
+            Point2::new(0.0, 0.0)
+        );
+        let edge = concentric_disk(&Point2::new(1.0, 0.5));
+        assert!((edge.x - 1.0).abs() < 1e-12 && edge.y.abs() < 1e-12);
+
+        for p in stratified_2d(64) {
+            let d = concentric_disk(&p);
+            assert!(d.x * d.x + d.y * d.y <= 1.0 + 1e-12);
+        }
+    }
+
+    #[test]
+    fn basis() {
+        for n in [
+            Vector3::I,
+            -Vector3::J,
+            Vector3::new(1.0, 2.0, 3.0).normalize(),
+        ] {
+            let (u, v) = orthonormal_basis(&n);
+            for (a, b) in [(u, n), (v, n), (u, v)] {
+                assert!(a.dot(&b).abs() < 1e-12);
+            }
+            assert!((u.magnitude() - 1.0).abs() < 1e-12);
+            assert!((v.magnitude() - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn cosine_power() {
+        let axis = Vector3::new(0.0, 1.0, 1.0).normalize();
+        let n = 20000;
+        let mean_cos = (0..n)
+            .map(|_| {
+                let d = cosine_power_direction(&axis, 1.0);
+                assert!((d.magnitude() - 1.0).abs() < 1e-9);
+                assert!(d.dot(&axis) >= 0.0);
+                d.dot(&axis)
+            })
+            .sum::<f64>()
+            / n as f64;
+
+        // the mean cosine of a cosine weighted hemisphere is 2/3
+        assert!((mean_cos - 2.0 / 3.0).abs() < 0.01, "{}", mean_cos);
+        assert_eq!(cosine_power_pdf(1.0, 1.0), 1.0 / PI);
+        assert_eq!(cosine_power_pdf(-0.5, 1.0), 0.0);
+    }
+}
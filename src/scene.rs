@@ -1,39 +1,97 @@
-use image::{DynamicImage, GenericImage};
+use std::sync::OnceLock;
+
+use image::DynamicImage;
 use indicatif::{ProgressBar, ProgressStyle};
-use itertools::Itertools;
+use rand::seq::SliceRandom;
 use rayon::prelude::*;
 
 use crate::{
-    algebra::{Ray, EPSILON},
+    algebra::Ray,
+    background::Background,
     camera::Camera,
     color::Color,
-    entity::Entity,
-    geometry::{Intersect, Intersection, Textured},
-    light::PointLight,
+    entity::{Entity, Group, Leaf},
+    film::{Film, Filter},
+    geometry::{Bounded, Bvh, Intersect, Intersection},
+    integrator::{Integrator, Whitted},
+    light::Light,
+    sampling,
+    tone_map::DisplayTransform,
 };
 
 pub struct Scene {
     pub width: u32,
     pub height: u32,
-    pub fov: f64,
     pub camera: Camera,
+    /// Entities of the scene. Changing them directly leaves the acceleration structure and the
+    /// emitters derived from them stale, so they are added with `add_entity` and changed with
+    /// `update_entity`.
     pub entities: Vec<Entity>,
-    pub lights: Vec<PointLight>,
-    pub background: Color,
+    pub lights: Vec<Light>,
+    pub background: Background,
     pub use_progress_bar: bool,
+    pub integrator: Box<dyn Integrator>,
+    pub samples_per_pixel: u32,
+    pub filter: Filter,
+    pub display: DisplayTransform,
+
+    emitters: Vec<Light>,
+    acceleration: OnceLock<Acceleration>,
+}
+
+/// Acceleration structure over the entities of the scene, built lazily on the first
+/// intersection after the entities change.
+struct Acceleration {
+    bvh: Bvh,
+    // entities without a bounding box, like planes, which are tested against every ray
+    unbounded: Vec<usize>,
+}
+
+impl Acceleration {
+    fn new(entities: &[Entity]) -> Self {
+        let bounds = entities
+            .iter()
+            .map(|entity| entity.bounding_box())
+            .collect::<Vec<_>>();
+
+        let unbounded = bounds
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.is_none())
+            .map(|(i, _)| i)
+            .collect();
+        let bvh = Bvh::new(
+            bounds
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, b)| b.map(|b| (i, b))),
+        );
+
+        Self { bvh, unbounded }
+    }
 }
 
 impl Scene {
-    pub fn new(width: u32, height: u32, fov: f64, camera: Camera, background: Color) -> Scene {
+    pub fn new(
+        width: u32,
+        height: u32,
+        camera: Camera,
+        background: impl Into<Background>,
+    ) -> Scene {
         Self {
             width,
             height,
-            fov,
             camera,
-            entities: Vec::new(),
             lights: Vec::new(),
-            background,
+            background: background.into(),
             use_progress_bar: false,
+            integrator: Box::new(Whitted::default()),
+            samples_per_pixel: 1,
+            filter: Filter::default(),
+            display: DisplayTransform::default(),
+            entities: Vec::new(),
+            emitters: Vec::new(),
+            acceleration: OnceLock::new(),
         }
     }
 
@@ -42,23 +100,80 @@ impl Scene {
         self
     }
 
+    /// Sets the algorithm computing the color of the camera rays, Whitted ray tracing by
+    /// default.
+    pub fn with_integrator(mut self, integrator: impl Integrator + 'static) -> Scene {
+        self.integrator = Box::new(integrator);
+        self
+    }
+
+    /// Sets the number of jittered rays shot through every pixel, which are averaged into the
+    /// final color.
+    pub fn with_samples_per_pixel(mut self, samples_per_pixel: u32) -> Scene {
+        self.samples_per_pixel = samples_per_pixel.max(1);
+        self
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Scene {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_display(mut self, display: DisplayTransform) -> Scene {
+        self.display = display;
+        self
+    }
+
     pub fn add_entity(&mut self, entity: Entity) {
-        self.entities.push(entity.build());
+        let entity = entity.build();
+        self.emitters.extend(entity.light());
+        self.entities.push(entity);
+        self.acceleration.take();
+    }
+
+    /// Changes the entity at `index` in place, for example to move it between the frames of
+    /// an animation. The acceleration structure over the entities is rebuilt, but not the
+    /// ones inside their geometry.
+    pub fn update_entity<F>(&mut self, index: usize, update: F)
+    where
+        F: FnOnce(&mut Entity),
+    {
+        update(&mut self.entities[index]);
+        self.emitters = self.entities.iter().filter_map(Entity::light).collect();
+        self.acceleration.take();
     }
 
-    pub fn add_light(&mut self, light: PointLight) {
-        self.lights.push(light);
+    pub fn add_light(&mut self, light: impl Into<Light>) {
+        self.lights.push(light.into());
+    }
+
+    /// Adds the entities and lights of a group, placed by the transforms of the hierarchy. A
+    /// camera in the group replaces the camera of the scene. Entities flattened by a singular
+    /// transform are added all the same, but rays never hit them.
+    pub fn add_group(&mut self, group: Group) {
+        for leaf in group.flatten() {
+            match leaf {
+                Leaf::Entity(entity) => self.add_entity(*entity),
+                Leaf::Light(light) => self.add_light(light),
+                Leaf::Camera(camera) => self.camera = camera,
+            }
+        }
+    }
+
+    /// Returns the lights sampling the surfaces of the emissive entities, for integrators that
+    /// can't rely on rays hitting them by chance.
+    pub fn emitters(&self) -> &[Light] {
+        &self.emitters
     }
 
     pub fn ray_to_screen_space(&self, x: i32, y: i32) -> Ray {
-        let width = self.width as i32;
-        let height = self.height as i32;
+        self.ray_to_subpixel(x as f64, y as f64)
+    }
 
-        let d = (width as f64 / 2.0) / (self.fov / 2.0).tan();
-        let raw = d * self.camera.view
-            + (x - (width / 2)) as f64 * self.camera.side
-            + ((height / 2) - y) as f64 * self.camera.up;
-        Ray::new(self.camera.position, raw)
+    /// Returns the ray through a point on the screen, where integer coordinates are the centers
+    /// of the pixels.
+    pub fn ray_to_subpixel(&self, x: f64, y: f64) -> Ray {
+        self.camera.ray(x, y, self.width, self.height)
     }
 
     pub fn set_size(&mut self, width: u32, height: u32) {
@@ -67,17 +182,25 @@ impl Scene {
     }
 
     pub fn render(&self) -> DynamicImage {
-        let mut img = DynamicImage::new_rgb8(self.width, self.height);
-
-        let width = self.width as i32;
-        let height = self.height as i32;
-        let max_y = height - 1;
+        self.render_film().to_image(&self.display)
+    }
 
-        let screen: Vec<(i32, i32)> = (0..width).cartesian_product(0..height).collect();
+    /// Renders the scene into a film holding the reconstructed linear radiance, before any
+    /// display transform.
+    pub fn render_film(&self) -> Film {
+        self.render_progressive(1, |_, _| {})
+    }
 
+    /// Renders the scene in `passes` passes of `samples_per_pixel` samples each, accumulating
+    /// them on the same film. `on_pass` is called with the number of finished passes and the
+    /// film after every pass, for example to save or display intermediate results.
+    pub fn render_progressive<F>(&self, passes: u32, mut on_pass: F) -> Film
+    where
+        F: FnMut(u32, &Film),
+    {
         let mut pb: Option<ProgressBar> = None;
         if self.use_progress_bar {
-            let bar = ProgressBar::new(screen.len() as u64);
+            let bar = ProgressBar::new(self.width as u64 * self.height as u64 * passes as u64);
             bar.set_style(ProgressStyle::default_bar().template(
                     "{spinner:.cyan} {msg:.green} [{elapsed_precise}] \
             {wide_bar:.magenta/white.dim} {percent}% ({eta})",
@@ -86,122 +209,186 @@ impl Scene {
             pb = Some(bar);
         }
 
-        let pixels: Vec<(i32, i32, Color)> = screen
-            .into_par_iter()
-            .map(|(x, y)| {
-                let ray = self.ray_to_screen_space(x, y);
-                let ret = (x, y, self.trace(ray.normalize(), 5));
-
-                if let Some(bar) = &pb {
-                    if y == max_y {
-                        bar.inc(height as u64);
-                    }
-                }
-
-                ret
-            })
-            .collect();
-
-        for (x, y, color) in pixels {
-            img.put_pixel(x as u32, y as u32, color.into());
+        let mut film = Film::new(self.width, self.height, self.filter);
+        for pass in 1..=passes {
+            self.render_pass(&mut film, pb.as_ref());
+            on_pass(pass, &film);
         }
 
         if let Some(bar) = &pb {
             bar.finish();
         }
 
-        return img;
+        film
     }
 
-    fn trace(&self, ray: Ray, depth: i32) -> Color {
-        if depth == 0 {
-            return self.background;
-        }
+    /// Renders one sample per pixel and stratum into `film`. Rows are traced in parallel in
+    /// bands of a few rows per thread, and their samples splatted onto the film between bands,
+    /// so that the threads don't each need a film of their own.
+    fn render_pass(&self, film: &mut Film, pb: Option<&ProgressBar>) {
+        let width = self.width as i32;
+        let height = self.height as i32;
+        let band = rayon::current_num_threads() * 4;
+
+        for start in (0..height).step_by(band) {
+            let end = (start + band as i32).min(height);
+            let rows = (start..end)
+                .into_par_iter()
+                .map(|y| {
+                    let mut samples =
+                        Vec::with_capacity(width as usize * self.samples_per_pixel.max(1) as usize);
+                    for x in 0..width {
+                        self.render_pixel(&mut samples, x, y);
+                    }
 
-        match self.closest_intersection(&ray) {
-            Some((
-                entity,
-                Intersection {
-                    position: intersect_point,
-                    normal,
-                    ..
-                },
-            )) => {
-                let material = entity.material();
-                let uv = entity.to_texture_space(&intersect_point);
-
-                let ka = material.ambient.color_at(&uv);
-                let kd = material.diffuse.color_at(&uv);
-                let ks = material.specular.color_at(&uv);
-                let alpha = material.shininess;
-                let reflectance = material.reflectance;
-
-                let n = normal;
-                let v = -ray.dir;
-                let offset_position = intersect_point + EPSILON * normal;
-
-                let local_color = self
-                    .lights
-                    .iter()
-                    .map(|light| {
-                        let intensity = light.intensity_at(&intersect_point);
-                        let ip = light.position;
-                        let ia = light.ambient;
-                        let id = light.diffuse;
-                        let is = light.specular;
-
-                        let l = (ip - intersect_point).normalize();
-                        let r = 2.0 * l.dot(&n) * n - l;
-
-                        let mut color = ka * ia * intensity;
-
-                        let shadow_ray = Ray::new(offset_position, l);
-
-                        let shadow_intersection = self.closest_intersection(&shadow_ray);
-                        let obstructed = match shadow_intersection {
-                            Some((_, Intersection { t, .. }))
-                                if (ip - offset_position).magnitude() > t =>
-                            {
-                                true
-                            }
-                            _ => false,
-                        };
-                        if obstructed {
-                            return color;
-                        }
-
-                        if l.dot(&n) > 0.0 {
-                            color = color + kd * l.dot(&n) * id * intensity;
-                        }
-
-                        if r.dot(&v) > 0.0 {
-                            color = color + ks * r.dot(&v).powf(alpha) * is * intensity;
-                        }
-
-                        color
-                    })
-                    .sum();
-
-                if reflectance == 0.0 {
-                    return local_color;
-                }
-
-                let r = 2.0 * (v.dot(&n)) * n - v;
-                let reflected_ray = Ray::new(offset_position, r);
-                let reflected_color = self.trace(reflected_ray, depth - 1);
-
-                let blended_color =
-                    reflected_color * reflectance + local_color * (1.0 - reflectance);
-                blended_color
+                    if let Some(bar) = pb {
+                        bar.inc(width as u64);
+                    }
+
+                    samples
+                })
+                .collect::<Vec<_>>();
+
+            for (x, y, color) in rows.into_iter().flatten() {
+                film.add_sample(x, y, color);
             }
-            None => self.background,
         }
     }
 
-    fn closest_intersection(&self, ray: &Ray) -> Option<(&Entity, Intersection)> {
-        self.entities
+    /// Traces the samples of a pixel, adding them to `samples` as the position they were
+    /// taken at and their color.
+    fn render_pixel(&self, samples: &mut Vec<(f64, f64, Color)>, x: i32, y: i32) {
+        // the field is public, so it may have been set to 0 without going through the builder
+        let count = self.samples_per_pixel.max(1);
+        let offsets = sampling::stratified_2d(count);
+        let mut lens = sampling::stratified_2d(count);
+        let mut times = sampling::stratified_1d(count);
+        // pair the positions on the pixel, on the lens and in time at random, so they aren't
+        // correlated
+        lens.shuffle(&mut rand::thread_rng());
+        times.shuffle(&mut rand::thread_rng());
+        for (offset, (lens, time)) in offsets.iter().zip(lens.iter().zip(&times)) {
+            let sx = x as f64 + offset.x - 0.5;
+            let sy = y as f64 + offset.y - 0.5;
+            let ray = self
+                .camera
+                .lens_ray(&self.ray_to_subpixel(sx, sy), lens)
+                .with_time(self.camera.time(*time))
+                .normalize();
+            let color = self.integrator.radiance(self, &ray);
+            samples.push((sx, sy, color));
+        }
+    }
+
+    /// Returns the closest entity hit by the ray, along with the intersection.
+    pub fn closest_intersection(&self, ray: &Ray) -> Option<(&Entity, Intersection)> {
+        self.closest_matching(ray, f64::INFINITY, |_| true)
+    }
+
+    /// Returns whether an entity casting shadows is hit by the ray closer than `distance`.
+    pub fn occluded(&self, ray: &Ray, distance: f64) -> bool {
+        self.closest_matching(ray, distance, Entity::casts_shadows)
+            .is_some()
+    }
+
+    /// Returns the closest hit closer than `t_max` among the entities accepted by `filter`.
+    fn closest_matching<F>(
+        &self,
+        ray: &Ray,
+        t_max: f64,
+        filter: F,
+    ) -> Option<(&Entity, Intersection)>
+    where
+        F: Fn(&Entity) -> bool,
+    {
+        let acceleration = self
+            .acceleration
+            .get_or_init(|| Acceleration::new(&self.entities));
+        let intersect = |i: usize| {
+            let entity = &self.entities[i];
+            if !filter(entity) {
+                return None;
+            }
+            entity.intersect(ray).filter(|x| x.t < t_max)
+        };
+
+        let unbounded = acceleration
+            .unbounded
             .iter()
-            .filter_map(|x| x.intersect(ray).and_then(|i| Some((x, i))))
-            .min_by(|(_, i1), (_, i2)| i1.partial_cmp(i2).unwrap())
+            .filter_map(|&i| intersect(i).map(|x| (i, x)))
+            .min_by(|(_, i1), (_, i2)| i1.partial_cmp(i2).unwrap());
+
+        // anything in the hierarchy has to be closer than the closest unbounded entity
+        let t_max = unbounded.map_or(t_max, |(_, x)| x.t);
+        let bounded = acceleration.bvh.intersect(ray, t_max, intersect);
+
+        bounded.or(unbounded).map(|(i, x)| (&self.entities[i], x))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algebra::Point3;
+
+    #[test]
+    fn subpixel_rays() {
+        let scene = Scene::new(
+            80,
+            60,
+            Camera::new(Point3::new(0.0, 0.0, -10.0)),
+            Color::BLACK,
+        );
+
+        assert_eq!(
+            scene.ray_to_screen_space(3, 7),
+            scene.ray_to_subpixel(3.0, 7.0)
+        );
+
+        let left = scene.ray_to_subpixel(2.5, 7.0).dir;
+        let right = scene.ray_to_subpixel(3.5, 7.0).dir;
+        let center = scene.ray_to_screen_space(3, 7).dir;
+        assert!((((left + right) * 0.5) - center).magnitude() < 1e-12);
+    }
+
+    #[test]
+    fn zero_samples_per_pixel() {
+        let mut scene = Scene::new(2, 2, Camera::new(Point3::O), Color::WHITE);
+        scene.samples_per_pixel = 0;
+
+        assert_eq!(scene.render_film().pixel(1, 1), Color::WHITE);
+    }
+
+    #[test]
+    fn add_group() {
+        use crate::{algebra::Vector3, entity::Transformable, geometry::Sphere, light::PointLight};
+
+        let mut scene = Scene::new(8, 8, Camera::new(Point3::O), Color::BLACK);
+        scene.add_group(
+            Group::new()
+                .with_child(Entity::from(Sphere::default()))
+                .with_child(Group::new().with_child(Light::from(PointLight::default())))
+                .with_child(Camera::new(Point3::new(0.0, 0.0, -5.0)))
+                .translate(Vector3::J),
+        );
+
+        assert_eq!(scene.entities.len(), 1);
+        assert_eq!(scene.lights.len(), 1);
+        assert_eq!(scene.camera.position, Point3::new(0.0, 1.0, -5.0));
+        assert!(scene
+            .closest_intersection(&scene.ray_to_screen_space(4, 4))
+            .is_some());
+
+        // a group flattening its children leaves them in the scene, out of reach of rays
+        let mut scene = Scene::new(8, 8, Camera::new(Point3::new(0.0, 0.0, -5.0)), Color::BLACK);
+        scene.add_group(
+            Group::new()
+                .with_child(Entity::from(Sphere::default()))
+                .scale(Vector3::ZERO),
+        );
+        assert_eq!(scene.entities.len(), 1);
+        assert!(scene
+            .closest_intersection(&scene.ray_to_screen_space(4, 4))
+            .is_none());
     }
 }
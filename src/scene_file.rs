@@ -0,0 +1,1270 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{
+    algebra::{Point3, Vector3},
+    background::{Background, Environment, EnvironmentMap},
+    camera::{Camera, Projection},
+    color::Color,
+    entity::{Entity, Motion, Transformable},
+    film::Filter,
+    geometry::{Axis, Cube, Geometry, Mesh, Plane, Sphere},
+    integrator::{AmbientOcclusion, Integrator, PathTracer, Whitted},
+    light::{AreaLight, AreaShape, DirectionalLight, Light, PointLight, SpotLight},
+    material::Phong,
+    scene::Scene,
+    texture::{Checker, Image, Texture},
+    tone_map::{DisplayTransform, ToneMap},
+};
+
+impl Scene {
+    /// Loads a scene from a TOML description. Paths to assets, like images and meshes, are
+    /// relative to the scene file.
+    pub fn from_file(path: &str) -> Result<Scene, String> {
+        let source = fs::read_to_string(path).map_err(|_| format!("Cannot read file {}", path))?;
+        let base_dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+
+        Self::from_toml(&source, base_dir).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Builds a scene from the contents of a TOML description, resolving asset paths against
+    /// `base_dir`.
+    pub fn from_toml(source: &str, base_dir: &Path) -> Result<Scene, String> {
+        let desc: SceneDesc = toml::from_str(source).map_err(|e| e.to_string())?;
+        desc.build(base_dir)
+    }
+}
+
+type ColorDesc = [f64; 3];
+
+fn color(c: &ColorDesc) -> Color {
+    Color::new(c[0], c[1], c[2])
+}
+
+fn point(p: &[f64; 3]) -> Point3 {
+    Point3::new(p[0], p[1], p[2])
+}
+
+fn vector(v: &[f64; 3]) -> Vector3 {
+    Vector3::new(v[0], v[1], v[2])
+}
+
+fn open_image(path: &Path, srgb: bool) -> Result<Image, String> {
+    if srgb {
+        Image::open_srgb(path)
+    } else {
+        Image::open(path)
+    }
+}
+
+fn default_fov() -> f64 {
+    70.0
+}
+
+fn default_samples() -> u32 {
+    1
+}
+
+fn default_one() -> f64 {
+    1.0
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_white() -> ColorDesc {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_up() -> [f64; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_down() -> [f64; 3] {
+    [0.0, -1.0, 0.0]
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDesc {
+    width: u32,
+    height: u32,
+    /// Horizontal field of view in degrees.
+    #[serde(default = "default_fov")]
+    fov: f64,
+    #[serde(default)]
+    background: BackgroundDesc,
+    #[serde(default)]
+    progress_bar: bool,
+    integrator: Option<IntegratorDesc>,
+    #[serde(default = "default_samples")]
+    samples_per_pixel: u32,
+    filter: Option<FilterDesc>,
+    camera: CameraDesc,
+    #[serde(default)]
+    display: DisplayDesc,
+    #[serde(default)]
+    materials: HashMap<String, MaterialDesc>,
+    #[serde(default)]
+    entities: Vec<EntityDesc>,
+    #[serde(default)]
+    lights: Vec<LightDesc>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDesc {
+    position: [f64; 3],
+    look_at: Option<[f64; 3]>,
+    /// Radius of the lens, 0 for a pinhole camera.
+    #[serde(default)]
+    aperture: f64,
+    focus_distance: Option<f64>,
+    /// Point to focus on, instead of a distance.
+    focus_on: Option<[f64; 3]>,
+    projection: Option<ProjectionDesc>,
+    /// Times the shutter opens and closes at.
+    shutter: Option<[f64; 2]>,
+}
+
+/// Camera projection, with angles in degrees. Perspective cameras default to the field of view
+/// of the scene.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum ProjectionDesc {
+    Perspective { fov: Option<f64> },
+    Orthographic { height: f64 },
+    Fisheye { fov: Option<f64> },
+    Equirectangular,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DisplayDesc {
+    /// Exposure in stops.
+    #[serde(default)]
+    exposure: f64,
+    #[serde(default)]
+    tone_map: ToneMapDesc,
+    #[serde(default)]
+    srgb: bool,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ToneMapDesc {
+    #[default]
+    Clamp,
+    Reinhard,
+    Aces,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum IntegratorDesc {
+    Whitted {
+        max_depth: Option<u32>,
+        ambient_occlusion: Option<AmbientOcclusionDesc>,
+    },
+    Path {
+        max_depth: Option<u32>,
+        roulette_depth: Option<u32>,
+    },
+    #[serde(rename = "ambient_occlusion")]
+    AmbientOcclusion {
+        samples: Option<u32>,
+        max_distance: Option<f64>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AmbientOcclusionDesc {
+    samples: Option<u32>,
+    max_distance: Option<f64>,
+}
+
+/// Reconstruction filter, any parameter left out takes a common default for the filter type.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum FilterDesc {
+    Box {
+        radius: Option<f64>,
+    },
+    Tent {
+        radius: Option<f64>,
+    },
+    Gaussian {
+        radius: Option<f64>,
+        alpha: Option<f64>,
+    },
+    Mitchell {
+        radius: Option<f64>,
+        b: Option<f64>,
+        c: Option<f64>,
+    },
+    Lanczos {
+        radius: Option<f64>,
+        tau: Option<f64>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialDesc {
+    ambient: Option<TextureDesc>,
+    diffuse: Option<TextureDesc>,
+    specular: Option<TextureDesc>,
+    shininess: Option<f64>,
+    #[serde(default)]
+    reflectance: f64,
+    #[serde(default)]
+    transparency: f64,
+    #[serde(default = "default_one")]
+    refractive_index: f64,
+    emission: Option<ColorDesc>,
+    #[serde(default = "default_one")]
+    emission_strength: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BackgroundDesc {
+    Color(ColorDesc),
+    Environment(EnvironmentDesc),
+}
+
+impl Default for BackgroundDesc {
+    fn default() -> Self {
+        Self::Color(ColorDesc::default())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EnvironmentDesc {
+    lat_long: Option<String>,
+    cube_map: Option<String>,
+    #[serde(default = "default_one")]
+    intensity: f64,
+    #[serde(default)]
+    light_samples: u32,
+    /// Whether the image is encoded for display and decoded with the inverse sRGB curve.
+    #[serde(default)]
+    srgb: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TextureDesc {
+    Color(ColorDesc),
+    Pattern(PatternDesc),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PatternDesc {
+    #[serde(default = "default_one")]
+    scale: f64,
+    color: Option<ColorDesc>,
+    checker: Option<[ColorDesc; 2]>,
+    image: Option<String>,
+    /// Whether `image` is encoded for display and decoded with the inverse sRGB curve.
+    #[serde(default)]
+    srgb: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MaterialRef {
+    Name(String),
+    Inline(Box<MaterialDesc>),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum GeometryDesc {
+    Sphere {
+        #[serde(default)]
+        center: [f64; 3],
+        #[serde(default = "default_one")]
+        radius: f64,
+    },
+    Plane {
+        #[serde(default)]
+        origin: [f64; 3],
+        #[serde(default = "default_up")]
+        normal: [f64; 3],
+    },
+    Cube {
+        min: Option<[f64; 3]>,
+        max: Option<[f64; 3]>,
+        #[serde(default)]
+        flipped_normals: bool,
+    },
+    Mesh {
+        path: String,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum AxisDesc {
+    X,
+    Y,
+    Z,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RotationDesc {
+    axis: AxisDesc,
+    degrees: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScaleDesc {
+    Uniform(f64),
+    Vector([f64; 3]),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EntityDesc {
+    geometry: GeometryDesc,
+    material: Option<MaterialRef>,
+    translate: Option<[f64; 3]>,
+    #[serde(default)]
+    rotate: Vec<RotationDesc>,
+    scale: Option<ScaleDesc>,
+    #[serde(default = "default_true")]
+    shadows: bool,
+    motion: Option<MotionDesc>,
+}
+
+/// Change of the transform over the shutter interval, from none at time 0 to all of it at time
+/// 1.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MotionDesc {
+    translate: Option<[f64; 3]>,
+    #[serde(default)]
+    rotate: Vec<RotationDesc>,
+    scale: Option<ScaleDesc>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LightDesc {
+    #[serde(rename = "type", default)]
+    kind: LightKind,
+    position: Option<[f64; 3]>,
+    /// Direction the light travels in, for directional lights, or the axis of a spot light.
+    direction: Option<[f64; 3]>,
+    /// Angles of the full strength and the outer cone of a spot light, in degrees.
+    inner_angle: Option<f64>,
+    outer_angle: Option<f64>,
+    /// Surface of an area light, and the number of samples taken across it.
+    shape: Option<AreaShapeDesc>,
+    samples: Option<u32>,
+    #[serde(default = "default_white")]
+    ambient: ColorDesc,
+    #[serde(default = "default_white")]
+    diffuse: ColorDesc,
+    #[serde(default = "default_white")]
+    specular: ColorDesc,
+    intensity: f64,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum LightKind {
+    #[default]
+    Point,
+    Directional,
+    Spot,
+    Area,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum AreaShapeDesc {
+    Rectangle {
+        center: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+    },
+    Disk {
+        center: [f64; 3],
+        #[serde(default = "default_down")]
+        normal: [f64; 3],
+        #[serde(default = "default_one")]
+        radius: f64,
+    },
+    Sphere {
+        center: [f64; 3],
+        #[serde(default = "default_one")]
+        radius: f64,
+    },
+}
+
+impl SceneDesc {
+    fn build(&self, base_dir: &Path) -> Result<Scene, String> {
+        let projection = match self.camera.projection {
+            None => Projection::Perspective {
+                fov: self.fov.to_radians(),
+            },
+            Some(ProjectionDesc::Perspective { fov }) => Projection::Perspective {
+                fov: fov.unwrap_or(self.fov).to_radians(),
+            },
+            Some(ProjectionDesc::Orthographic { height }) => Projection::Orthographic { height },
+            Some(ProjectionDesc::Fisheye { fov }) => Projection::Fisheye {
+                fov: fov.unwrap_or(180.0).to_radians(),
+            },
+            Some(ProjectionDesc::Equirectangular) => Projection::Equirectangular,
+        };
+        let mut camera = Camera::new(point(&self.camera.position)).with_projection(projection);
+        if let Some(target) = &self.camera.look_at {
+            camera.look_at(point(target));
+        }
+        camera.aperture = self.camera.aperture;
+        if let Some([open, close]) = self.camera.shutter {
+            if open > close {
+                return Err("camera.shutter: the shutter closes before it opens".to_string());
+            }
+            camera = camera.with_shutter(open, close);
+        }
+        match (self.camera.focus_distance, &self.camera.focus_on) {
+            (Some(_), Some(_)) => {
+                return Err("camera: expected at most one of `focus_distance` or `focus_on`".into())
+            }
+            (Some(distance), None) => camera.focus_distance = distance,
+            (None, Some(target)) => camera.focus_on(point(target)),
+            (None, None) => {}
+        }
+
+        let mut scene = Scene::new(
+            self.width,
+            self.height,
+            camera,
+            self.background
+                .build(base_dir)
+                .map_err(|e| format!("background{}", e))?,
+        );
+        if self.progress_bar {
+            scene = scene.with_progress_bar();
+        }
+        if let Some(integrator) = &self.integrator {
+            scene.integrator = integrator.build();
+        }
+        scene = scene
+            .with_samples_per_pixel(self.samples_per_pixel)
+            .with_display(self.display.build());
+        if let Some(filter) = &self.filter {
+            scene = scene.with_filter(filter.build());
+        }
+
+        let mut materials = HashMap::new();
+        for (name, desc) in &self.materials {
+            let material = desc
+                .build(base_dir)
+                .map_err(|e| format!("materials.{}.{}", name, e))?;
+            materials.insert(name.as_str(), material);
+        }
+
+        for (i, desc) in self.entities.iter().enumerate() {
+            let entity = desc
+                .build(base_dir, &materials)
+                .map_err(|e| format!("entities[{}].{}", i, e))?;
+            scene.add_entity(entity);
+        }
+
+        for (i, desc) in self.lights.iter().enumerate() {
+            let light = desc.build().map_err(|e| format!("lights[{}].{}", i, e))?;
+            scene.add_light(light);
+        }
+
+        Ok(scene)
+    }
+}
+
+impl IntegratorDesc {
+    fn build(&self) -> Box<dyn Integrator> {
+        match *self {
+            Self::Whitted {
+                max_depth,
+                ambient_occlusion: ref ao,
+            } => {
+                let mut whitted = Whitted::new(max_depth.unwrap_or(Whitted::default().max_depth));
+                if let Some(ao) = ao {
+                    whitted = whitted
+                        .with_ambient_occlusion(ambient_occlusion(ao.samples, ao.max_distance));
+                }
+                Box::new(whitted)
+            }
+            Self::Path {
+                max_depth,
+                roulette_depth,
+            } => {
+                let default = PathTracer::default();
+                Box::new(
+                    PathTracer::new(max_depth.unwrap_or(default.max_depth))
+                        .with_roulette_depth(roulette_depth.unwrap_or(default.roulette_depth)),
+                )
+            }
+            Self::AmbientOcclusion {
+                samples,
+                max_distance,
+            } => Box::new(ambient_occlusion(samples, max_distance)),
+        }
+    }
+}
+
+fn ambient_occlusion(samples: Option<u32>, max_distance: Option<f64>) -> AmbientOcclusion {
+    let default = AmbientOcclusion::default();
+    AmbientOcclusion::new(
+        samples.unwrap_or(default.samples),
+        max_distance.unwrap_or(default.max_distance),
+    )
+}
+
+impl AreaShapeDesc {
+    fn build(&self) -> AreaShape {
+        match self {
+            Self::Rectangle { center, u, v } => AreaShape::Rectangle {
+                center: point(center),
+                u: vector(u),
+                v: vector(v),
+            },
+            Self::Disk {
+                center,
+                normal,
+                radius,
+            } => AreaShape::Disk {
+                center: point(center),
+                normal: vector(normal),
+                radius: *radius,
+            },
+            Self::Sphere { center, radius } => AreaShape::Sphere {
+                center: point(center),
+                radius: *radius,
+            },
+        }
+    }
+}
+
+impl FilterDesc {
+    fn build(&self) -> Filter {
+        match *self {
+            Self::Box { radius } => Filter::Box {
+                radius: radius.unwrap_or(0.5),
+            },
+            Self::Tent { radius } => Filter::Tent {
+                radius: radius.unwrap_or(1.0),
+            },
+            Self::Gaussian { radius, alpha } => Filter::Gaussian {
+                radius: radius.unwrap_or(1.5),
+                alpha: alpha.unwrap_or(2.0),
+            },
+            Self::Mitchell { radius, b, c } => Filter::Mitchell {
+                radius: radius.unwrap_or(2.0),
+                b: b.unwrap_or(1.0 / 3.0),
+                c: c.unwrap_or(1.0 / 3.0),
+            },
+            Self::Lanczos { radius, tau } => Filter::Lanczos {
+                radius: radius.unwrap_or(3.0),
+                tau: tau.unwrap_or(3.0),
+            },
+        }
+    }
+}
+
+impl DisplayDesc {
+    fn build(&self) -> DisplayTransform {
+        let tone_map = match self.tone_map {
+            ToneMapDesc::Clamp => ToneMap::Clamp,
+            ToneMapDesc::Reinhard => ToneMap::Reinhard,
+            ToneMapDesc::Aces => ToneMap::Aces,
+        };
+
+        let mut display = DisplayTransform::new(tone_map).with_exposure(self.exposure);
+        if self.srgb {
+            display = display.with_srgb();
+        }
+        display
+    }
+}
+
+impl MaterialDesc {
+    fn build(&self, base_dir: &Path) -> Result<Phong, String> {
+        let default = Phong::default();
+        let texture = |desc: &Option<TextureDesc>, field: &str, default: Texture| match desc {
+            Some(desc) => desc.build(base_dir).map_err(|e| format!("{}{}", field, e)),
+            None => Ok(default),
+        };
+
+        Ok(Phong::new(
+            texture(&self.ambient, "ambient", default.ambient)?,
+            texture(&self.diffuse, "diffuse", default.diffuse)?,
+            texture(&self.specular, "specular", default.specular)?,
+            self.shininess.unwrap_or(default.shininess),
+        )
+        .with_reflectance(self.reflectance)
+        .with_transparency(self.transparency)
+        .with_refractive_index(self.refractive_index)
+        .with_emission(
+            self.emission.as_ref().map_or(Color::BLACK, color),
+            self.emission_strength,
+        ))
+    }
+}
+
+impl BackgroundDesc {
+    fn build(&self, base_dir: &Path) -> Result<Background, String> {
+        let env = match self {
+            Self::Color(c) => return Ok(Background::from(color(c))),
+            Self::Environment(env) => env,
+        };
+
+        let open = |path: &String, field: &str| {
+            open_image(&base_dir.join(path), env.srgb)
+                .map_err(|_| format!(".{}: Cannot read image {}", field, path))
+        };
+        let map = match (&env.lat_long, &env.cube_map) {
+            (Some(path), None) => EnvironmentMap::LatLong(open(path, "lat_long")?),
+            (None, Some(path)) => EnvironmentMap::CrossCubeMap(open(path, "cube_map")?),
+            _ => return Err(": expected exactly one of `lat_long` or `cube_map`".to_string()),
+        };
+
+        Ok(Environment::new(map)
+            .with_intensity(env.intensity)
+            .with_light_samples(env.light_samples)
+            .into())
+    }
+}
+
+impl TextureDesc {
+    fn build(&self, base_dir: &Path) -> Result<Texture, String> {
+        let pattern = match self {
+            Self::Color(c) => return Ok(Texture::from(color(c))),
+            Self::Pattern(pattern) => pattern,
+        };
+
+        match (&pattern.color, &pattern.checker, &pattern.image) {
+            (Some(c), None, None) => Ok(Texture::new(pattern.scale, color(c))),
+            (None, Some([a, b]), None) => Ok(Texture::new(
+                pattern.scale,
+                Checker::new(color(a), color(b)),
+            )),
+            (None, None, Some(path)) => {
+                let image = open_image(&base_dir.join(path), pattern.srgb)
+                    .map_err(|_| format!(".image: Cannot read image {}", path))?;
+                Ok(Texture::new(pattern.scale, image))
+            }
+            _ => Err(": expected exactly one of `color`, `checker` or `image`".to_string()),
+        }
+    }
+}
+
+impl LightDesc {
+    fn build(&self) -> Result<Light, String> {
+        let (ambient, diffuse, specular) = (
+            color(&self.ambient),
+            color(&self.diffuse),
+            color(&self.specular),
+        );
+
+        let light = match self.kind {
+            LightKind::Point => {
+                let position = self
+                    .position
+                    .as_ref()
+                    .ok_or("position: missing for a point light")?;
+                PointLight::new(point(position), ambient, diffuse, specular, self.intensity).into()
+            }
+            LightKind::Directional => {
+                let direction = self
+                    .direction
+                    .as_ref()
+                    .ok_or("direction: missing for a directional light")?;
+                DirectionalLight::new(
+                    vector(direction),
+                    ambient,
+                    diffuse,
+                    specular,
+                    self.intensity,
+                )
+                .into()
+            }
+            LightKind::Spot => {
+                let position = self
+                    .position
+                    .as_ref()
+                    .ok_or("position: missing for a spot light")?;
+                let direction = self
+                    .direction
+                    .as_ref()
+                    .ok_or("direction: missing for a spot light")?;
+                let outer_angle = self
+                    .outer_angle
+                    .ok_or("outer_angle: missing for a spot light")?;
+                let inner_angle = self.inner_angle.unwrap_or(outer_angle);
+                if inner_angle > outer_angle {
+                    return Err("inner_angle: larger than outer_angle".to_string());
+                }
+
+                SpotLight::new(
+                    point(position),
+                    vector(direction),
+                    inner_angle.to_radians(),
+                    outer_angle.to_radians(),
+                    ambient,
+                    diffuse,
+                    specular,
+                    self.intensity,
+                )
+                .into()
+            }
+            LightKind::Area => {
+                let shape = self
+                    .shape
+                    .as_ref()
+                    .ok_or("shape: missing for an area light")?;
+                let mut light =
+                    AreaLight::new(shape.build(), ambient, diffuse, specular, self.intensity);
+                if let Some(samples) = self.samples {
+                    light = light.with_samples(samples);
+                }
+                light.into()
+            }
+        };
+
+        // like unknown keys, keys that only other kinds of lights use are rejected rather than
+        // silently ignored
+        let keys = [
+            ("position", self.position.is_some()),
+            ("direction", self.direction.is_some()),
+            ("inner_angle", self.inner_angle.is_some()),
+            ("outer_angle", self.outer_angle.is_some()),
+            ("shape", self.shape.is_some()),
+            ("samples", self.samples.is_some()),
+        ];
+        if let Some((key, _)) = keys
+            .iter()
+            .find(|(key, given)| *given && !self.kind.uses(key))
+        {
+            return Err(format!(
+                "{}: unexpected for {}",
+                key,
+                self.kind.description()
+            ));
+        }
+
+        Ok(light)
+    }
+}
+
+impl LightKind {
+    /// Returns whether lights of this kind are built with the optional key `key`.
+    fn uses(&self, key: &str) -> bool {
+        matches!(
+            (self, key),
+            (Self::Point | Self::Spot, "position")
+                | (Self::Directional | Self::Spot, "direction")
+                | (Self::Spot, "inner_angle" | "outer_angle")
+                | (Self::Area, "shape" | "samples")
+        )
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Self::Point => "a point light",
+            Self::Directional => "a directional light",
+            Self::Spot => "a spot light",
+            Self::Area => "an area light",
+        }
+    }
+}
+
+impl EntityDesc {
+    fn build(&self, base_dir: &Path, materials: &HashMap<&str, Phong>) -> Result<Entity, String> {
+        let geometry: Geometry = match &self.geometry {
+            GeometryDesc::Sphere { center, radius } => Sphere::new(point(center), *radius).into(),
+            GeometryDesc::Plane { origin, normal } => {
+                Plane::new(point(origin), vector(normal).normalize()).into()
+            }
+            GeometryDesc::Cube {
+                min,
+                max,
+                flipped_normals,
+            } => {
+                let mut cube = match (min, max) {
+                    (Some(min), Some(max)) => Cube::new(point(min), point(max)),
+                    (None, None) => Cube::default(),
+                    _ => return Err("geometry: expected both `min` and `max`".to_string()),
+                };
+                if *flipped_normals {
+                    cube = cube.with_flipped_normals();
+                }
+                cube.into()
+            }
+            GeometryDesc::Mesh { path } => {
+                let full_path = base_dir.join(path);
+                let full_path = full_path.to_str().unwrap_or_default();
+                let mesh = if path.ends_with(".obj") {
+                    Mesh::from_obj_file(full_path)
+                } else {
+                    Mesh::from_ply_file(full_path)
+                };
+                mesh.map_err(|e| format!("geometry.path: {}: {}", path, e))?
+                    .into()
+            }
+        };
+
+        let mut entity = Entity::from(geometry);
+        if !self.shadows {
+            entity = entity.without_shadows();
+        }
+
+        match &self.material {
+            Some(MaterialRef::Name(name)) => {
+                let material = materials
+                    .get(name.as_str())
+                    .ok_or_else(|| format!("material: unknown material `{}`", name))?;
+                entity = entity.with_material(material.clone());
+            }
+            Some(MaterialRef::Inline(desc)) => {
+                let material = desc
+                    .build(base_dir)
+                    .map_err(|e| format!("material.{}", e))?;
+                entity = entity.with_material(material);
+            }
+            None => {}
+        }
+
+        if let Some(motion) = &self.motion {
+            entity = entity.with_motion(transformed(
+                Motion::new(),
+                &motion.rotate,
+                &motion.scale,
+                &motion.translate,
+            ));
+        }
+
+        Ok(transformed(
+            entity,
+            &self.rotate,
+            &self.scale,
+            &self.translate,
+        ))
+    }
+}
+
+/// Applies the rotations, then the scale and then the translation of a description.
+fn transformed<T: Transformable>(
+    mut target: T,
+    rotate: &[RotationDesc],
+    scale: &Option<ScaleDesc>,
+    translate: &Option<[f64; 3]>,
+) -> T {
+    for rotation in rotate {
+        let axis = match rotation.axis {
+            AxisDesc::X => Axis::X,
+            AxisDesc::Y => Axis::Y,
+            AxisDesc::Z => Axis::Z,
+        };
+        target = target.rotate(axis, rotation.degrees);
+    }
+
+    match scale {
+        Some(ScaleDesc::Uniform(s)) => target = target.scale(Vector3::new(*s, *s, *s)),
+        Some(ScaleDesc::Vector(s)) => target = target.scale(vector(s)),
+        None => {}
+    }
+
+    if let Some(translation) = translate {
+        target = target.translate(vector(translation));
+    }
+
+    target
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = r#"
+        width = 80
+        height = 60
+        fov = 90.0
+        background = [0.0, 0.03, 0.03]
+
+        [camera]
+        position = [0.0, 0.0, -35.0]
+        look_at = [0.0, 0.0, 0.0]
+
+        [materials.floor]
+        diffuse = { scale = 5.0, checker = [[0.4, 0.4, 0.4], [0.03, 0.03, 0.03]] }
+
+        [[entities]]
+        geometry = { type = "plane", origin = [0.0, -10.0, 0.0] }
+        material = "floor"
+
+        [[entities]]
+        geometry = { type = "sphere" }
+        material = { diffuse = [0.0, 1.0, 0.0], reflectance = 0.5, transparency = 0.9, refractive_index = 1.5 }
+        scale = 5.0
+        rotate = [{ axis = "y", degrees = 45.0 }]
+        translate = [-5.0, 0.0, 5.0]
+
+        [[lights]]
+        position = [0.0, 20.0, -12.0]
+        intensity = 300.0
+    "#;
+
+    #[test]
+    fn from_toml() {
+        let scene = Scene::from_toml(SCENE, Path::new("")).unwrap();
+
+        assert_eq!((scene.width, scene.height), (80, 60));
+        assert_eq!(
+            scene.camera.projection,
+            Projection::Perspective {
+                fov: 90.0_f64.to_radians()
+            }
+        );
+        assert_eq!(scene.camera.view, Vector3::K);
+        assert_eq!(scene.entities.len(), 2);
+        assert_eq!(scene.entities[1].material().reflectance, 0.5);
+        assert_eq!(scene.entities[1].material().refractive_index, 1.5);
+        assert_eq!(scene.entities[0].material().refractive_index, 1.0);
+        assert_eq!(scene.lights.len(), 1);
+        assert_eq!(scene.lights[0].diffuse(), Color::WHITE);
+        assert_eq!(
+            format!("{:?}", scene.integrator),
+            format!("{:?}", Whitted::default())
+        );
+        assert_eq!(scene.samples_per_pixel, 1);
+        assert_eq!(scene.filter, Filter::default());
+        assert_eq!(scene.display, DisplayTransform::default());
+    }
+
+    #[test]
+    fn integrator_and_filter() {
+        let source = SCENE.replace(
+            "[camera]",
+            "integrator = { type = \"path\", max_depth = 8 }\n\
+             filter = { type = \"mitchell\", b = 0.5 }\n\n[camera]",
+        );
+        let scene = Scene::from_toml(&source, Path::new("")).unwrap();
+
+        assert_eq!(
+            format!("{:?}", scene.integrator),
+            format!("{:?}", PathTracer::new(8))
+        );
+        assert_eq!(
+            scene.filter,
+            Filter::Mitchell {
+                radius: 2.0,
+                b: 0.5,
+                c: 1.0 / 3.0
+            }
+        );
+    }
+
+    #[test]
+    fn ambient_occlusion() {
+        let source = SCENE.replace(
+            "[camera]",
+            "[integrator]\n\
+             type = \"whitted\"\n\
+             ambient_occlusion = { samples = 4, max_distance = 2.0 }\n\n[camera]",
+        );
+        let scene = Scene::from_toml(&source, Path::new("")).unwrap();
+
+        assert_eq!(
+            format!("{:?}", scene.integrator),
+            format!(
+                "{:?}",
+                Whitted::default().with_ambient_occlusion(AmbientOcclusion::new(4, 2.0))
+            )
+        );
+    }
+
+    #[test]
+    fn directional_light() {
+        let source = format!(
+            "{}\n[[lights]]\ntype = \"directional\"\ndirection = [0.0, -2.0, 0.0]\nintensity = 1.0\n",
+            SCENE
+        );
+        let scene = Scene::from_toml(&source, Path::new("")).unwrap();
+
+        assert_eq!(
+            scene.lights[1],
+            Light::from(DirectionalLight::new(
+                -Vector3::J,
+                Color::WHITE,
+                Color::WHITE,
+                Color::WHITE,
+                1.0
+            ))
+        );
+
+        let source = source.replace("direction =", "position =");
+        let error = Scene::from_toml(&source, Path::new("")).err().unwrap();
+        assert_eq!(
+            error,
+            "lights[1].direction: missing for a directional light"
+        );
+
+        let source = source.replace(
+            "type = \"directional\"",
+            "type = \"directional\"\ndirection = [0.0, -1.0, 0.0]",
+        );
+        let error = Scene::from_toml(&source, Path::new("")).err().unwrap();
+        assert_eq!(
+            error,
+            "lights[1].position: unexpected for a directional light"
+        );
+    }
+
+    #[test]
+    fn spot_light() {
+        let source = format!(
+            "{}\n[[lights]]\ntype = \"spot\"\nposition = [0.0, 5.0, 0.0]\ndirection = [0.0, -1.0, 0.0]\ninner_angle = 20.0\nouter_angle = 30.0\nintensity = 10.0\n",
+            SCENE
+        );
+        let scene = Scene::from_toml(&source, Path::new("")).unwrap();
+
+        assert_eq!(
+            scene.lights[1],
+            Light::from(SpotLight::new(
+                Point3::new(0.0, 5.0, 0.0),
+                -Vector3::J,
+                20.0_f64.to_radians(),
+                30.0_f64.to_radians(),
+                Color::WHITE,
+                Color::WHITE,
+                Color::WHITE,
+                10.0
+            ))
+        );
+
+        let source = source.replace("inner_angle = 20.0", "inner_angle = 40.0");
+        let error = Scene::from_toml(&source, Path::new("")).err().unwrap();
+        assert_eq!(error, "lights[1].inner_angle: larger than outer_angle");
+    }
+
+    #[test]
+    fn area_light() {
+        let source = format!(
+            "{}\n[[lights]]\ntype = \"area\"\nshape = {{ type = \"disk\", center = [0.0, 5.0, 0.0], radius = 2.0 }}\nsamples = 4\nintensity = 10.0\n",
+            SCENE
+        );
+        let scene = Scene::from_toml(&source, Path::new("")).unwrap();
+
+        assert_eq!(
+            scene.lights[1],
+            Light::from(
+                AreaLight::new(
+                    AreaShape::Disk {
+                        center: Point3::new(0.0, 5.0, 0.0),
+                        normal: -Vector3::J,
+                        radius: 2.0
+                    },
+                    Color::WHITE,
+                    Color::WHITE,
+                    Color::WHITE,
+                    10.0
+                )
+                .with_samples(4)
+            )
+        );
+
+        let source = source.replace("shape =", "# shape =");
+        let error = Scene::from_toml(&source, Path::new("")).err().unwrap();
+        assert_eq!(error, "lights[1].shape: missing for an area light");
+    }
+
+    #[test]
+    fn emissive_entity() {
+        let source = format!(
+            "{}\n[[entities]]\ngeometry = {{ type = \"cube\" }}\nmaterial = {{ emission = [1.0, 0.5, 0.0], emission_strength = 4.0 }}\n",
+            SCENE
+        );
+        let scene = Scene::from_toml(&source, Path::new("")).unwrap();
+
+        assert_eq!(
+            scene.entities[2].material().emission,
+            Color::new(4.0, 2.0, 0.0)
+        );
+        assert_eq!(scene.entities[1].material().emission, Color::BLACK);
+        assert_eq!(scene.emitters().len(), 1);
+    }
+
+    #[test]
+    fn display_transform() {
+        let source = SCENE.replace(
+            "[camera]",
+            "[display]\nexposure = -1.5\ntone_map = \"aces\"\nsrgb = true\n\n[camera]",
+        );
+        let scene = Scene::from_toml(&source, Path::new("")).unwrap();
+
+        assert_eq!(
+            scene.display,
+            DisplayTransform::new(ToneMap::Aces)
+                .with_exposure(-1.5)
+                .with_srgb()
+        );
+    }
+
+    #[test]
+    fn syntax_error_has_line() {
+        let source = SCENE.replace("[camera]", "[camera");
+        let error = Scene::from_toml(&source, Path::new("")).err().unwrap();
+
+        assert!(error.contains("line 7"), "{}", error);
+    }
+
+    #[test]
+    fn unknown_material_has_field() {
+        let source = SCENE.replace("material = \"floor\"", "material = \"wall\"");
+        let error = Scene::from_toml(&source, Path::new("")).err().unwrap();
+
+        assert_eq!(error, "entities[0].material: unknown material `wall`");
+    }
+
+    #[test]
+    fn missing_asset_has_field() {
+        let source = SCENE.replace(
+            "type = \"sphere\"",
+            "type = \"mesh\", path = \"missing.ply\"",
+        );
+        let error = Scene::from_toml(&source, Path::new("assets"))
+            .err()
+            .unwrap();
+
+        assert!(
+            error.starts_with("entities[1].geometry.path: missing.ply:"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn environment_background() {
+        let source = SCENE.replace(
+            "background = [0.0, 0.03, 0.03]",
+            "background = { cube_map = \"space_cubemap.jpg\", intensity = 2.0, light_samples = 8 }",
+        );
+        let scene = Scene::from_toml(&source, Path::new("assets")).unwrap();
+        match scene.background.light() {
+            Some(env) => {
+                assert_eq!(env.intensity, 2.0);
+                assert_eq!(env.light_samples, 8);
+            }
+            None => panic!("{:?}", scene.background),
+        }
+
+        let decoded = source.replace("intensity = 2.0", "srgb = true, intensity = 2.0");
+        let decoded = Scene::from_toml(&decoded, Path::new("assets")).unwrap();
+        let dir = Vector3::new(0.3, 0.2, 1.0).normalize();
+        let (raw, linear) = (
+            scene.background.radiance(&dir),
+            decoded.background.radiance(&dir),
+        );
+        assert!(linear.r < raw.r, "{:?} {:?}", linear, raw);
+        assert_eq!(linear, (raw * 0.5).to_linear() * 2.0);
+
+        let source = source.replace("cube_map", "lat_long = \"earth.jpg\", cube_map");
+        let error = Scene::from_toml(&source, Path::new("assets"))
+            .err()
+            .unwrap();
+        assert_eq!(
+            error,
+            "background: expected exactly one of `lat_long` or `cube_map`"
+        );
+    }
+
+    #[test]
+    fn depth_of_field() {
+        let source = SCENE.replace(
+            "[camera]",
+            "[camera]\naperture = 0.5\nfocus_on = [3.0, 1.0, 5.0]",
+        );
+        let scene = Scene::from_toml(&source, Path::new("")).unwrap();
+        assert_eq!(scene.camera.aperture, 0.5);
+        assert_eq!(scene.camera.focus_distance, 40.0);
+
+        let source = source.replace("aperture", "focus_distance = 2.0\naperture");
+        let error = Scene::from_toml(&source, Path::new("")).err().unwrap();
+        assert_eq!(
+            error,
+            "camera: expected at most one of `focus_distance` or `focus_on`"
+        );
+    }
+
+    #[test]
+    fn projections() {
+        let projection = |desc: &str| {
+            let source = SCENE.replace("[camera]", &format!("[camera]\nprojection = {}", desc));
+            Scene::from_toml(&source, Path::new(""))
+                .unwrap()
+                .camera
+                .projection
+        };
+
+        assert_eq!(
+            projection("{ type = \"perspective\" }"),
+            Projection::Perspective {
+                fov: 90.0_f64.to_radians()
+            }
+        );
+        assert_eq!(
+            projection("{ type = \"orthographic\", height = 12.0 }"),
+            Projection::Orthographic { height: 12.0 }
+        );
+        assert_eq!(
+            projection("{ type = \"fisheye\" }"),
+            Projection::Fisheye {
+                fov: 180.0_f64.to_radians()
+            }
+        );
+        assert_eq!(
+            projection("{ type = \"equirectangular\" }"),
+            Projection::Equirectangular
+        );
+    }
+
+    #[test]
+    fn motion_blur() {
+        use crate::{algebra::Ray, geometry::Intersect};
+
+        let source = SCENE
+            .replace("[camera]", "[camera]\nshutter = [0.0, 0.5]")
+            .replace(
+                "translate = [-5.0, 0.0, 5.0]",
+                "translate = [-5.0, 0.0, 5.0]\nmotion = { translate = [4.0, 0.0, 0.0] }",
+            );
+        let scene = Scene::from_toml(&source, Path::new("")).unwrap();
+        assert_eq!(
+            (scene.camera.shutter_open, scene.camera.shutter_close),
+            (0.0, 0.5)
+        );
+
+        // the sphere of radius 5 moves from x = -5 to x = -1
+        let sphere = &scene.entities[1];
+        let ray = Ray::new(Point3::new(3.5, 0.0, -10.0), Vector3::K);
+        assert!(sphere.intersect(&ray).is_none());
+        assert!(sphere.intersect(&ray.with_time(1.0)).is_some());
+
+        let source = source.replace("[0.0, 0.5]", "[0.5, 0.0]");
+        let error = Scene::from_toml(&source, Path::new("")).err().unwrap();
+        assert_eq!(error, "camera.shutter: the shutter closes before it opens");
+    }
+}
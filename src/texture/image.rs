@@ -1,29 +1,316 @@
-use image::DynamicImage;
-use image::GenericImageView;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use image::codecs::hdr::{HdrDecoder, HdrEncoder};
+use image::{DynamicImage, Rgb, Rgba};
 
 use super::ColoredTexture;
 use super::TextureCoordinate;
 use crate::color::Color;
 
+/// Image with floating point pixels, holding linear radiance without quantization.
+///
+/// Radiance `.hdr` and portable float map `.pfm` files keep their full range when opened or
+/// saved, any other format goes through 8 bits per channel. The 8 bit values are taken as
+/// linear when opened and written back clamped, as the default display transform does, use
+/// [`Image::open_srgb`] for images encoded for display. OpenEXR is not supported by the
+/// version of the `image` crate in use, PFM is the closest lossless alternative.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Image {
-    image: DynamicImage,
+    width: u32,
+    height: u32,
+    // rows from top to bottom
+    pixels: Vec<Color>,
+}
+
+impl Image {
+    /// Creates an image from its pixels, row by row starting at the top.
+    pub fn new(width: u32, height: u32, pixels: Vec<Color>) -> Self {
+        assert_eq!(
+            Some(pixels.len()),
+            (width as usize).checked_mul(height as usize)
+        );
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Converts an image encoded for display, decoding it with the inverse sRGB curve.
+    pub fn from_srgb(image: DynamicImage) -> Self {
+        let mut image = Self::from(image);
+        for pixel in image.pixels.iter_mut() {
+            *pixel = pixel.to_linear();
+        }
+        image
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, String> {
+        Self::open_with(path.as_ref(), false)
+    }
+
+    /// Opens an image like [`Image::open`], decoding 8 bit formats with the inverse sRGB curve.
+    pub fn open_srgb(path: impl AsRef<Path>) -> Result<Self, String> {
+        Self::open_with(path.as_ref(), true)
+    }
+
+    fn open_with(path: &Path, srgb: bool) -> Result<Self, String> {
+        let error = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+
+        match extension(path).as_str() {
+            "hdr" => {
+                let file = File::open(path).map_err(|e| error(&e))?;
+                let decoder = HdrDecoder::new(BufReader::new(file)).map_err(|e| error(&e))?;
+                let metadata = decoder.metadata();
+                let pixels = decoder.read_image_hdr().map_err(|e| error(&e))?;
+
+                Ok(Self::new(
+                    metadata.width,
+                    metadata.height,
+                    pixels
+                        .iter()
+                        .map(|p| Color::new(p[0] as f64, p[1] as f64, p[2] as f64))
+                        .collect(),
+                ))
+            }
+            "pfm" => {
+                let data = fs::read(path).map_err(|e| error(&e))?;
+                read_pfm(&data).map_err(|e| error(&e))
+            }
+            _ => {
+                let image = image::open(path).map_err(|e| error(&e))?;
+                Ok(if srgb {
+                    Self::from_srgb(image)
+                } else {
+                    Self::from(image)
+                })
+            }
+        }
+    }
+
+    /// Saves the image, picking the format from the extension of the path.
+    ///
+    /// Formats other than `.hdr` and `.pfm` get the radiance clamped to 8 bits without any
+    /// tone mapping or encoding, pass the image through a display transform first, for
+    /// instance with [`Film::to_image`](crate::film::Film::to_image), to save it for display.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let error = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+
+        match extension(path).as_str() {
+            "hdr" => {
+                let file = File::create(path).map_err(|e| error(&e))?;
+                let pixels = self
+                    .pixels
+                    .iter()
+                    .map(|c| Rgb([c.r as f32, c.g as f32, c.b as f32]))
+                    .collect::<Vec<_>>();
+                HdrEncoder::new(BufWriter::new(file))
+                    .encode(&pixels, self.width as usize, self.height as usize)
+                    .map_err(|e| error(&e))
+            }
+            "pfm" => {
+                let mut file = BufWriter::new(File::create(path).map_err(|e| error(&e))?);
+                file.write_all(&self.to_pfm()).map_err(|e| error(&e))
+            }
+            "exr" => Err(error(
+                &"OpenEXR is not supported, save as .hdr or .pfm instead",
+            )),
+            _ => self.to_ldr().save(path).map_err(|e| error(&e)),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Converts the image to 8 bits per channel, clamping every component. No display transform
+    /// is applied, so the values are written as linear.
+    pub fn to_ldr(&self) -> DynamicImage {
+        let mut image = image::RgbImage::new(self.width, self.height);
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            let Rgba([r, g, b, _]) = self.pixel(x, y).into();
+            *pixel = Rgb([r, g, b]);
+        }
+        DynamicImage::ImageRgb8(image)
+    }
+
+    fn to_pfm(&self) -> Vec<u8> {
+        let mut data = format!("PF\n{} {}\n-1.0\n", self.width, self.height).into_bytes();
+        // rows are stored from the bottom up, in little endian since the scale is negative
+        for y in (0..self.height).rev() {
+            for x in 0..self.width {
+                let c = self.pixel(x, y);
+                for v in [c.r, c.g, c.b] {
+                    data.extend_from_slice(&(v as f32).to_le_bytes());
+                }
+            }
+        }
+        data
+    }
 }
 
+/// The values are taken as they are stored, as linear, see [`Image::from_srgb`] for images
+/// encoded for display.
 impl From<DynamicImage> for Image {
     fn from(image: DynamicImage) -> Self {
-        Self { image }
+        let image = image.to_rgba8();
+        Self::new(
+            image.width(),
+            image.height(),
+            image.pixels().map(|p| Color::from(*p)).collect(),
+        )
     }
 }
 
 impl ColoredTexture for Image {
     fn color_at(&self, p: TextureCoordinate) -> Color {
-        let w = (self.image.width() - 1) as f64;
-        let h = (self.image.height() - 1) as f64;
+        let w = (self.width - 1) as f64;
+        let h = (self.height - 1) as f64;
         let x = (p.u() * w).round() as u32;
         let y = ((1.0 - p.v()) * h).round() as u32;
 
-        //dbg!(self.image.get_pixel(x, y).into())
-        self.image.get_pixel(x, y).into()
+        self.pixel(x, y)
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|x| x.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// Parses a portable float map, in color (`PF`) or grayscale (`Pf`).
+fn read_pfm(data: &[u8]) -> Result<Image, String> {
+    // the header is four whitespace separated tokens, the type, the size and the scale, ended
+    // by a single whitespace byte
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut offset = None;
+    for (i, b) in data.iter().enumerate() {
+        if !b.is_ascii_whitespace() {
+            start.get_or_insert(i);
+        } else if let Some(s) = start.take() {
+            tokens.push(String::from_utf8_lossy(&data[s..i]).into_owned());
+            if tokens.len() == 4 {
+                offset = Some(i + 1);
+                break;
+            }
+        }
+    }
+    let offset = offset.ok_or("Truncated PFM header")?;
+
+    let channels = match tokens[0].as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err("Not a PFM file".to_string()),
+    };
+    let size = |s: &str| s.parse::<u32>().map_err(|_| "Invalid PFM size".to_string());
+    let (width, height) = (size(&tokens[1])?, size(&tokens[2])?);
+    let scale = tokens[3]
+        .parse::<f64>()
+        .map_err(|_| "Invalid PFM scale".to_string())?;
+
+    let (w, h) = (width as usize, height as usize);
+    let pixel_count = w
+        .checked_mul(h)
+        .filter(|n| n.checked_mul(channels * 4).is_some())
+        .ok_or("Invalid PFM size")?;
+    let count = pixel_count * channels;
+    let body = &data[offset..];
+    if body.len() < count * 4 {
+        return Err("Truncated PFM data".to_string());
+    }
+
+    let values = body[..count * 4]
+        .chunks_exact(4)
+        .map(|b| {
+            let b = [b[0], b[1], b[2], b[3]];
+            let v = if scale < 0.0 {
+                f32::from_le_bytes(b)
+            } else {
+                f32::from_be_bytes(b)
+            };
+            v as f64
+        })
+        .collect::<Vec<f64>>();
+
+    let mut pixels = vec![Color::BLACK; pixel_count];
+    for (i, v) in values.chunks_exact(channels).enumerate() {
+        let (x, y) = (i % w, i / w);
+        let c = if channels == 3 {
+            Color::new(v[0], v[1], v[2])
+        } else {
+            Color::new(v[0], v[0], v[0])
+        };
+        pixels[(h - 1 - y) * w + x] = c;
+    }
+
+    Ok(Image::new(width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient() -> Image {
+        let pixels = (0..6)
+            .map(|i| Color::new(i as f64 * 10.0, 0.25, -(i as f64)))
+            .collect();
+        Image::new(3, 2, pixels)
+    }
+
+    #[test]
+    fn pfm_round_trip() {
+        let path = std::env::temp_dir().join("raytox_round_trip.pfm");
+        let image = gradient();
+        image.save(&path).unwrap();
+
+        assert_eq!(Image::open(&path).unwrap(), image);
+        assert!(fs::read(&path).unwrap().starts_with(b"PF\n3 2\n-1.0\n"));
+
+        let error = read_pfm(b"PF\n4294967295 4294967295\n-1.0\n");
+        assert_eq!(error, Err("Invalid PFM size".to_string()));
+    }
+
+    #[test]
+    fn hdr_keeps_range() {
+        let path = std::env::temp_dir().join("raytox_round_trip.hdr");
+        let pixels = vec![Color::new(100.0, 0.5, 0.001), Color::WHITE * 4.0];
+        Image::new(2, 1, pixels.clone()).save(&path).unwrap();
+
+        let image = Image::open(&path).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 1));
+        for (x, expected) in pixels.iter().enumerate() {
+            let c = image.pixel(x as u32, 0);
+            // RGBE shares one exponent between the channels, so small ones lose precision
+            assert!((c.r - expected.r).abs() <= expected.r / 100.0, "{:?}", c);
+            assert!((c.g - expected.g).abs() <= expected.r / 100.0, "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn unsupported_and_ldr() {
+        let error = gradient().save("out.exr").err().unwrap();
+        assert!(error.contains("OpenEXR is not supported"), "{}", error);
+
+        let ldr = Image::from(gradient().to_ldr());
+        assert_eq!(ldr.pixel(0, 0), Color::new(0.0, 63.0 / 255.0, 0.0));
+        assert_eq!(ldr.pixel(2, 1), Color::new(1.0, 63.0 / 255.0, 0.0));
+
+        let srgb = Image::from_srgb(gradient().to_ldr());
+        assert_eq!(srgb.pixel(2, 1).r, 1.0);
+        assert!((srgb.pixel(0, 0).g - 0.0497).abs() < 1e-4, "{:?}", srgb);
     }
 }
@@ -65,8 +65,8 @@ impl Texture {
 
     pub fn color_at(&self, p: &Point2) -> Color {
         let uv = TextureCoordinate(Point2::new(
-            ((*p).x / self.scale).rem_euclid(1.0),
-            ((*p).y / self.scale).rem_euclid(1.0),
+            (p.x / self.scale).rem_euclid(1.0),
+            (p.y / self.scale).rem_euclid(1.0),
         ));
 
         match &self.data {
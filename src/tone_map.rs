@@ -0,0 +1,117 @@
+use crate::color::Color;
+
+/// Operator compressing unbounded linear radiance into the displayable [0, 1] range.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum ToneMap {
+    /// Clamps every component, so anything brighter than 1 is blown out.
+    #[default]
+    Clamp,
+    /// Reinhard's global operator applied to the luminance, keeping the hue of bright colors.
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve.
+    Aces,
+}
+
+impl ToneMap {
+    pub fn apply(&self, c: Color) -> Color {
+        match self {
+            Self::Clamp => c.clamp(),
+            Self::Reinhard => {
+                let luminance = c.luminance();
+                if luminance <= 0.0 {
+                    return Color::BLACK;
+                }
+                (c * (1.0 / (1.0 + luminance))).clamp()
+            }
+            Self::Aces => Color::new(aces(c.r), aces(c.g), aces(c.b)),
+        }
+    }
+}
+
+fn aces(x: f64) -> f64 {
+    let x = x.max(0.0);
+    ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0)
+}
+
+/// Display transform applied to every pixel of a render: exposure, then tone mapping, then
+/// the output encoding.
+///
+/// The default transform only clamps, leaving the linear values as they are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayTransform {
+    /// Exposure adjustment in stops, the radiance is scaled by `2^exposure`.
+    pub exposure: f64,
+    pub tone_map: ToneMap,
+    /// Whether to gamma encode the output with the sRGB transfer function.
+    pub srgb: bool,
+}
+
+impl DisplayTransform {
+    pub fn new(tone_map: ToneMap) -> Self {
+        Self {
+            exposure: 0.0,
+            tone_map,
+            srgb: false,
+        }
+    }
+
+    pub fn with_exposure(mut self, exposure: f64) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    pub fn with_srgb(mut self) -> Self {
+        self.srgb = true;
+        self
+    }
+
+    /// Maps a linear radiance value to a display color with components in [0, 1].
+    pub fn apply(&self, c: Color) -> Color {
+        let mapped = self.tone_map.apply(c * self.exposure.exp2());
+        if self.srgb {
+            mapped.to_srgb()
+        } else {
+            mapped
+        }
+    }
+}
+
+impl Default for DisplayTransform {
+    fn default() -> Self {
+        Self::new(ToneMap::Clamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operators_stay_in_range() {
+        let bright = Color::new(100.0, 4.0, 0.5);
+        for tone_map in [ToneMap::Clamp, ToneMap::Reinhard, ToneMap::Aces] {
+            let c = tone_map.apply(bright);
+            for x in [c.r, c.g, c.b] {
+                assert!((0.0..=1.0).contains(&x), "{:?} gave {:?}", tone_map, c);
+            }
+            assert_eq!(tone_map.apply(Color::BLACK), Color::BLACK);
+        }
+    }
+
+    #[test]
+    fn reinhard_keeps_hue_of_dim_colors() {
+        let c = ToneMap::Reinhard.apply(Color::new(0.2, 0.1, 0.0));
+        assert!((c.r / c.g - 2.0).abs() < 1e-9);
+        assert!(c.r < 0.2);
+    }
+
+    #[test]
+    fn exposure_and_srgb() {
+        let transform = DisplayTransform::default().with_exposure(1.0);
+        assert_eq!(transform.apply(Color::WHITE * 0.25), Color::WHITE * 0.5);
+
+        let transform = DisplayTransform::default().with_srgb();
+        let c = transform.apply(Color::WHITE * 0.5);
+        assert!((c.r - 0.7354).abs() < 1e-4);
+    }
+}